@@ -0,0 +1,112 @@
+use crate::lexer::Span;
+
+// every node keeps the span of the source text it was parsed from.
+
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub functions: Vec<Function>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Ident {
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: Ident,
+    pub params: Vec<Param>,
+    pub body: Block,
+    pub span: Span,
+}
+
+// parameters are always integers, e.g. 'int a'.
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: Ident,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum StmtKind {
+    // 'int a;', 'int a = 10;', 'int[100] a;'
+    Declare { ty: VarType, name: Ident, init: Option<Expr> },
+
+    // 'a = 10;', 'a[i] = 10;'
+    Assign { target: Place, value: Expr },
+
+    While { cond: Expr, body: Block },
+    If { cond: Expr, then_block: Block, else_block: Option<Block> },
+    Break,
+    Continue,
+
+    Print(Expr),
+    Read(Place),
+    Return(Expr),
+
+    // a call used as a statement, e.g. 'foo(1, 2);'
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VarType {
+    Int,
+    Array(i32),
+}
+
+// something that can be written to, a variable or an array element.
+#[derive(Debug, Clone)]
+pub struct Place {
+    pub name: Ident,
+    pub index: Option<Expr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExprKind {
+    Num(i32),
+    Var(String),
+    Index { array: Ident, index: Box<Expr> },
+    Call { name: Ident, args: Vec<Expr> },
+    Unary { op: UnaryOp, expr: Box<Expr> },
+    Binary { op: BinaryOp, lhs: Box<Expr>, rhs: Box<Expr> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+}
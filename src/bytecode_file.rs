@@ -0,0 +1,650 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::interpreter::{name_slots, Bytecode, FunctionBytecode, MemRead, MemWrite, Op, Program, VariableType};
+
+// a parsed program saved as binary, so it can run without lexing and parsing the IR again.
+//
+//     "TTBC"        magic
+//     u16           format version
+//     u16           reserved, 0
+//     u32           payload length in bytes
+//     u32           CRC-32 of the payload
+//     payload
+//
+// the payload holds the host functions the program calls (name, arity) and every function
+// with its variable and label tables, the IR line of each instruction for backtraces, and
+// the instructions with their slots, jump targets and call indices already resolved.
+// numbers are little endian, strings are a u32 byte length followed by UTF-8.
+//
+// a file is checked as thoroughly as IR text before it runs, the VM trusts every slot,
+// jump target and call index it is given.
+
+const MAGIC: &[u8; 4] = b"TTBC";
+pub const VERSION: u16 = 1;
+const HEADER_LEN: usize = 16;
+
+#[derive(Debug)]
+pub enum LoadError {
+    NotBytecode,
+    UnsupportedVersion(u16),
+    Truncated,
+    Checksum,
+    Invalid(String),
+}
+
+impl std::error::Error for LoadError {}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+        LoadError::NotBytecode => write!(f, "not a bytecode file, it does not start with 'TTBC'."),
+        LoadError::UnsupportedVersion(version) => write!(f, "bytecode version {version} is not supported, this VM reads version {VERSION}."),
+        LoadError::Truncated => write!(f, "the bytecode file is truncated."),
+        LoadError::Checksum => write!(f, "the bytecode file is corrupted, its checksum does not match."),
+        LoadError::Invalid(message) => write!(f, "invalid bytecode. {message}"),
+        }
+    }
+}
+
+// CRC-32 as used by zip and png.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc: u32 = 0xffffffff;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+// instruction opcodes in the file, never renumber these.
+const END: u8 = 0;
+const LABEL: u8 = 1;
+const INT: u8 = 2;
+const INT_ARRAY: u8 = 3;
+const OUT: u8 = 4;
+const IN: u8 = 5;
+const MOV: u8 = 6;
+const ADD: u8 = 7;
+const SUB: u8 = 8;
+const MULT: u8 = 9;
+const DIV: u8 = 10;
+const MOD: u8 = 11;
+const CALL: u8 = 12;
+const CALL_HOST: u8 = 13;
+const LESS_THAN: u8 = 14;
+const LESS_EQUAL: u8 = 15;
+const NOT_EQUAL: u8 = 16;
+const EQUAL: u8 = 17;
+const GREATER_EQUAL: u8 = 18;
+const GREATER_THAN: u8 = 19;
+const RETURN: u8 = 20;
+const JMP: u8 = 21;
+const BRANCH_IF: u8 = 22;
+const BRANCH_IFN: u8 = 23;
+
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn u32(&mut self, value: usize) {
+        self.bytes.extend_from_slice(&(value as u32).to_le_bytes());
+    }
+
+    fn i32(&mut self, value: i32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn str(&mut self, value: &str) {
+        self.u32(value.len());
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    fn op(&mut self, op: &Op) {
+        match op {
+        Op::Num(num) => {
+            self.u8(0);
+            self.i32(*num);
+        }
+        Op::Var(id) => {
+            self.u8(1);
+            self.u32(*id);
+        }
+        }
+    }
+
+    fn ops3(&mut self, opcode: u8, dest: usize, src1: &Op, src2: &Op) {
+        self.u8(opcode);
+        self.u32(dest);
+        self.op(src1);
+        self.op(src2);
+    }
+
+    fn call(&mut self, opcode: u8, dest: usize, callee: usize, params: &[Op]) {
+        self.u8(opcode);
+        self.u32(dest);
+        self.u32(callee);
+        self.u32(params.len());
+        for param in params {
+            self.op(param);
+        }
+    }
+
+    fn instruction(&mut self, instr: &Bytecode) {
+        match instr {
+        Bytecode::End => self.u8(END),
+        Bytecode::Label => self.u8(LABEL),
+        Bytecode::Int(id) => {
+            self.u8(INT);
+            self.u32(*id);
+        }
+        Bytecode::IntArray(id) => {
+            self.u8(INT_ARRAY);
+            self.u32(*id);
+        }
+        Bytecode::Out(value) => {
+            self.u8(OUT);
+            self.op(value);
+        }
+        Bytecode::In(id) => {
+            self.u8(IN);
+            self.u32(*id);
+        }
+        Bytecode::Mov(dest, src) => {
+            self.u8(MOV);
+            match dest {
+            MemWrite::IntVar(id) => {
+                self.u8(0);
+                self.u32(*id);
+            }
+            MemWrite::ArrayWrite(id, index) => {
+                self.u8(1);
+                self.u32(*id);
+                self.op(index);
+            }
+            }
+            match src {
+            MemRead::IntVar(id) => {
+                self.u8(0);
+                self.u32(*id);
+            }
+            MemRead::Number(num) => {
+                self.u8(1);
+                self.i32(*num);
+            }
+            MemRead::ArrayRead(id, index) => {
+                self.u8(2);
+                self.u32(*id);
+                self.op(index);
+            }
+            }
+        }
+        Bytecode::Add(dest, src1, src2) => self.ops3(ADD, *dest, src1, src2),
+        Bytecode::Sub(dest, src1, src2) => self.ops3(SUB, *dest, src1, src2),
+        Bytecode::Mult(dest, src1, src2) => self.ops3(MULT, *dest, src1, src2),
+        Bytecode::Div(dest, src1, src2) => self.ops3(DIV, *dest, src1, src2),
+        Bytecode::Mod(dest, src1, src2) => self.ops3(MOD, *dest, src1, src2),
+        Bytecode::LessThan(dest, src1, src2) => self.ops3(LESS_THAN, *dest, src1, src2),
+        Bytecode::LessEqual(dest, src1, src2) => self.ops3(LESS_EQUAL, *dest, src1, src2),
+        Bytecode::NotEqual(dest, src1, src2) => self.ops3(NOT_EQUAL, *dest, src1, src2),
+        Bytecode::Equal(dest, src1, src2) => self.ops3(EQUAL, *dest, src1, src2),
+        Bytecode::GreaterEqual(dest, src1, src2) => self.ops3(GREATER_EQUAL, *dest, src1, src2),
+        Bytecode::GreaterThan(dest, src1, src2) => self.ops3(GREATER_THAN, *dest, src1, src2),
+        Bytecode::Call(dest, callee, params) => self.call(CALL, *dest, *callee, params),
+        Bytecode::CallHost(dest, host_id, params) => self.call(CALL_HOST, *dest, *host_id, params),
+        Bytecode::Return(value) => {
+            self.u8(RETURN);
+            self.op(value);
+        }
+        Bytecode::Jmp(target) => {
+            self.u8(JMP);
+            self.u32(*target);
+        }
+        Bytecode::BranchIf(value, target) => {
+            self.u8(BRANCH_IF);
+            self.op(value);
+            self.u32(*target);
+        }
+        Bytecode::BranchIfn(value, target) => {
+            self.u8(BRANCH_IFN);
+            self.op(value);
+            self.u32(*target);
+        }
+        }
+    }
+
+    fn function(&mut self, function: &FunctionBytecode) {
+        self.str(&function.name);
+        self.u32(function.parameters);
+        self.u32(function.int_slots);
+        self.u32(function.array_lengths.len());
+        for len in &function.array_lengths {
+            self.u32(*len);
+        }
+
+        // sorted, so the same program always gives the same file.
+        let mut variables: Vec<(&String, &VariableType)> = function.variables.iter().collect();
+        variables.sort_by_key(|(name, _)| *name);
+        self.u32(variables.len());
+        for (name, variable) in variables {
+            self.str(name);
+            match variable {
+            VariableType::IntVar(id) => {
+                self.u8(0);
+                self.u32(*id);
+            }
+            VariableType::ArrayVar(id) => {
+                self.u8(1);
+                self.u32(*id);
+            }
+            }
+        }
+
+        let mut labels: Vec<(&String, &usize)> = function.labels.iter().collect();
+        labels.sort_by_key(|(name, _)| *name);
+        self.u32(labels.len());
+        for (name, index) in labels {
+            self.str(name);
+            self.u32(*index);
+        }
+
+        self.u32(function.body.len());
+        for (instr, line) in function.body.iter().zip(&function.lines) {
+            self.u32(*line);
+            self.instruction(instr);
+        }
+    }
+}
+
+pub fn save(program: &Program) -> Vec<u8> {
+    let mut payload = Writer { bytes: vec![] };
+    payload.u32(program.host_signature.len());
+    for (name, arity) in &program.host_signature {
+        payload.str(name);
+        payload.u32(*arity);
+    }
+    payload.u32(program.functions.len());
+    for function in &program.functions {
+        payload.function(function);
+    }
+
+    let mut file = Writer { bytes: Vec::with_capacity(HEADER_LEN + payload.bytes.len()) };
+    file.bytes.extend_from_slice(MAGIC);
+    file.bytes.extend_from_slice(&VERSION.to_le_bytes());
+    file.bytes.extend_from_slice(&0u16.to_le_bytes());
+    file.u32(payload.bytes.len());
+    file.bytes.extend_from_slice(&crc32(&payload.bytes).to_le_bytes());
+    file.bytes.extend_from_slice(&payload.bytes);
+    file.bytes
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+fn invalid<T>(message: String) -> Result<T, LoadError> {
+    Err(LoadError::Invalid(message))
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], LoadError> {
+        let end = self.position.checked_add(len).filter(|end| *end <= self.bytes.len()).ok_or(LoadError::Truncated)?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, LoadError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<usize, LoadError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()) as usize)
+    }
+
+    fn i32(&mut self) -> Result<i32, LoadError> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn str(&mut self) -> Result<String, LoadError> {
+        let len = self.u32()?;
+        match String::from_utf8(self.take(len)?.to_vec()) {
+        Ok(s) => Ok(s),
+        Err(_) => invalid(String::from("a name is not valid UTF-8.")),
+        }
+    }
+
+    // a count of following entries that each take at least 'min_size' bytes, checked
+    // against what is left so a corrupted count can not ask for a huge allocation.
+    fn count(&mut self, min_size: usize) -> Result<usize, LoadError> {
+        let count = self.u32()?;
+        if count.saturating_mul(min_size) > self.bytes.len() - self.position {
+            return Err(LoadError::Truncated);
+        }
+        Ok(count)
+    }
+
+    fn op(&mut self) -> Result<Op, LoadError> {
+        match self.u8()? {
+        0 => Ok(Op::Num(self.i32()?)),
+        1 => Ok(Op::Var(self.u32()?)),
+        tag => invalid(format!("unknown operand kind {tag}.")),
+        }
+    }
+
+    fn call(&mut self) -> Result<(usize, usize, Vec<Op>), LoadError> {
+        let dest = self.u32()?;
+        let callee = self.u32()?;
+        let count = self.count(5)?;
+        let mut params = vec![];
+        for _ in 0..count {
+            params.push(self.op()?);
+        }
+        Ok((dest, callee, params))
+    }
+
+    fn instruction(&mut self) -> Result<Bytecode, LoadError> {
+        let instr = match self.u8()? {
+        END => Bytecode::End,
+        LABEL => Bytecode::Label,
+        INT => Bytecode::Int(self.u32()?),
+        INT_ARRAY => Bytecode::IntArray(self.u32()?),
+        OUT => Bytecode::Out(self.op()?),
+        IN => Bytecode::In(self.u32()?),
+        MOV => {
+            let dest = match self.u8()? {
+            0 => MemWrite::IntVar(self.u32()?),
+            1 => MemWrite::ArrayWrite(self.u32()?, self.op()?),
+            tag => return invalid(format!("unknown %mov destination kind {tag}.")),
+            };
+            let src = match self.u8()? {
+            0 => MemRead::IntVar(self.u32()?),
+            1 => MemRead::Number(self.i32()?),
+            2 => MemRead::ArrayRead(self.u32()?, self.op()?),
+            tag => return invalid(format!("unknown %mov source kind {tag}.")),
+            };
+            Bytecode::Mov(dest, src)
+        }
+        CALL => {
+            let (dest, callee, params) = self.call()?;
+            Bytecode::Call(dest, callee, params)
+        }
+        CALL_HOST => {
+            let (dest, host_id, params) = self.call()?;
+            Bytecode::CallHost(dest, host_id, params)
+        }
+        opcode @ (ADD..=MOD | LESS_THAN..=GREATER_THAN) => {
+            let (dest, src1, src2) = (self.u32()?, self.op()?, self.op()?);
+            match opcode {
+            ADD => Bytecode::Add(dest, src1, src2),
+            SUB => Bytecode::Sub(dest, src1, src2),
+            MULT => Bytecode::Mult(dest, src1, src2),
+            DIV => Bytecode::Div(dest, src1, src2),
+            MOD => Bytecode::Mod(dest, src1, src2),
+            LESS_THAN => Bytecode::LessThan(dest, src1, src2),
+            LESS_EQUAL => Bytecode::LessEqual(dest, src1, src2),
+            NOT_EQUAL => Bytecode::NotEqual(dest, src1, src2),
+            EQUAL => Bytecode::Equal(dest, src1, src2),
+            GREATER_EQUAL => Bytecode::GreaterEqual(dest, src1, src2),
+            _ => Bytecode::GreaterThan(dest, src1, src2),
+            }
+        }
+        RETURN => Bytecode::Return(self.op()?),
+        JMP => Bytecode::Jmp(self.u32()?),
+        BRANCH_IF => Bytecode::BranchIf(self.op()?, self.u32()?),
+        BRANCH_IFN => Bytecode::BranchIfn(self.op()?, self.u32()?),
+        opcode => return invalid(format!("unknown opcode {opcode}.")),
+        };
+        Ok(instr)
+    }
+
+    fn function(&mut self) -> Result<FunctionBytecode, LoadError> {
+        let name = self.str()?;
+        let parameters = self.u32()?;
+        let int_slots = self.u32()?;
+        let mut array_lengths = vec![];
+        for _ in 0..self.count(4)? {
+            array_lengths.push(self.u32()?);
+        }
+
+        let mut variables = HashMap::new();
+        for _ in 0..self.count(9)? {
+            let name = self.str()?;
+            let variable = match self.u8()? {
+            0 => VariableType::IntVar(self.u32()?),
+            1 => VariableType::ArrayVar(self.u32()?),
+            tag => return invalid(format!("unknown variable kind {tag}.")),
+            };
+            variables.insert(name, variable);
+        }
+
+        let mut labels = HashMap::new();
+        for _ in 0..self.count(8)? {
+            let name = self.str()?;
+            labels.insert(name, self.u32()?);
+        }
+
+        let mut body = vec![];
+        let mut lines = vec![];
+        for _ in 0..self.count(5)? {
+            lines.push(self.u32()?);
+            body.push(self.instruction()?);
+        }
+
+        // the slot names are filled in by load once the tables are checked.
+        Ok(FunctionBytecode {
+            name, parameters, int_slots, array_lengths, variables, body, lines, labels,
+            int_names: vec![], array_names: vec![],
+        })
+    }
+}
+
+// everything the parser guarantees about resolved IR, which the VM relies on.
+fn check_function(function: &FunctionBytecode, functions: &[FunctionBytecode], host_signature: &[(String, usize)]) -> Result<(), String> {
+    let ints = function.int_slots;
+    let arrays = function.array_lengths.len();
+    let len = function.body.len();
+    let int = |id: &usize| if *id < ints { Ok(()) } else { Err(format!("integer slot {id} out of range")) };
+    let array = |id: &usize| if *id < arrays { Ok(()) } else { Err(format!("array slot {id} out of range")) };
+    let op = |op: &Op| match op {
+    Op::Num(_) => Ok(()),
+    Op::Var(id) => int(id),
+    };
+    let target = |index: &usize| if *index < len { Ok(()) } else { Err(format!("jump target {index} out of range")) };
+
+    if function.parameters > ints {
+        return Err(String::from("more parameters than integer slots"));
+    }
+    // every slot belongs to a named variable, as in parsed IR. this also keeps a corrupted
+    // slot count from allocating more than the variable table can account for.
+    let int_vars = function.variables.values().filter(|v| matches!(v, VariableType::IntVar(_))).count();
+    if int_vars != ints || function.variables.len() - int_vars != arrays {
+        return Err(format!("{ints} integer and {arrays} array slots for {int_vars} integer and {} array variables", function.variables.len() - int_vars));
+    }
+    if !matches!(function.body.last(), Some(Bytecode::End)) {
+        return Err(String::from("the body does not end with %endfunc"));
+    }
+    for variable in function.variables.values() {
+        match variable {
+        VariableType::IntVar(id) => int(id)?,
+        VariableType::ArrayVar(id) => array(id)?,
+        }
+    }
+    for index in function.labels.values() {
+        target(index)?;
+    }
+
+    for instr in &function.body {
+        match instr {
+        Bytecode::End | Bytecode::Label => {}
+        Bytecode::Int(id) | Bytecode::In(id) => int(id)?,
+        Bytecode::IntArray(id) => array(id)?,
+        Bytecode::Out(value) | Bytecode::Return(value) => op(value)?,
+        Bytecode::Mov(dest, src) => {
+            match dest {
+            MemWrite::IntVar(id) => int(id)?,
+            MemWrite::ArrayWrite(id, index) => {
+                array(id)?;
+                op(index)?;
+            }
+            }
+            match src {
+            MemRead::IntVar(id) => int(id)?,
+            MemRead::Number(_) => {}
+            MemRead::ArrayRead(id, index) => {
+                array(id)?;
+                op(index)?;
+            }
+            }
+        }
+        Bytecode::Add(dest, src1, src2) | Bytecode::Sub(dest, src1, src2) | Bytecode::Mult(dest, src1, src2)
+        | Bytecode::Div(dest, src1, src2) | Bytecode::Mod(dest, src1, src2)
+        | Bytecode::LessThan(dest, src1, src2) | Bytecode::LessEqual(dest, src1, src2) | Bytecode::NotEqual(dest, src1, src2)
+        | Bytecode::Equal(dest, src1, src2) | Bytecode::GreaterEqual(dest, src1, src2) | Bytecode::GreaterThan(dest, src1, src2) => {
+            int(dest)?;
+            op(src1)?;
+            op(src2)?;
+        }
+        Bytecode::Call(dest, callee, params) => {
+            int(dest)?;
+            if *callee >= functions.len() {
+                return Err(format!("call to function {callee} out of range"));
+            }
+            params.iter().try_for_each(op)?;
+        }
+        Bytecode::CallHost(dest, host_id, params) => {
+            int(dest)?;
+            // host functions index their arguments without checking, the arity has to match.
+            match host_signature.get(*host_id) {
+            Some((_, arity)) if *arity == params.len() => {}
+            _ => return Err(format!("call to host function {host_id} does not match its signature")),
+            }
+            params.iter().try_for_each(op)?;
+        }
+        Bytecode::Jmp(index) => target(index)?,
+        Bytecode::BranchIf(value, index) | Bytecode::BranchIfn(value, index) => {
+            op(value)?;
+            target(index)?;
+        }
+        }
+    }
+    Ok(())
+}
+
+pub fn load(bytes: &[u8]) -> Result<Program, LoadError> {
+    if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+        return Err(LoadError::NotBytecode);
+    }
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if version != VERSION {
+        return Err(LoadError::UnsupportedVersion(version));
+    }
+    let len = u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize;
+    let checksum = u32::from_le_bytes(bytes[12..16].try_into().unwrap());
+    let payload = &bytes[HEADER_LEN..];
+    if payload.len() < len {
+        return Err(LoadError::Truncated);
+    }
+    if payload.len() > len {
+        return invalid(String::from("there is data after the end of the program."));
+    }
+    if crc32(payload) != checksum {
+        return Err(LoadError::Checksum);
+    }
+
+    let mut reader = Reader { bytes: payload, position: 0 };
+    let mut host_signature = vec![];
+    for _ in 0..reader.count(8)? {
+        let name = reader.str()?;
+        host_signature.push((name, reader.u32()?));
+    }
+    let mut functions = vec![];
+    for _ in 0..reader.count(25)? {
+        functions.push(reader.function()?);
+    }
+    if reader.position != payload.len() {
+        return invalid(String::from("there is data after the last function."));
+    }
+
+    for function in &functions {
+        if let Err(message) = check_function(function, &functions, &host_signature) {
+            return invalid(format!("function '{}': {message}.", function.name));
+        }
+    }
+    for function in &mut functions {
+        name_slots(function);
+    }
+    Ok(Program { functions, source_map: None, host_signature })
+}
+
+#[cfg(test)]
+mod bytecode_file_tests {
+    use crate::bytecode_file::*;
+    use crate::host::HostRegistry;
+    use crate::interpreter::VmConfig;
+
+    fn program() -> Program {
+        let code = "%func fact(%int n)\n    %int c\n    %lt c, n, 2\n    %branch_ifn c, :rec\n    %ret 1\n:rec\n    %sub c, n, 1\n    %call c, fact(c)\n    %mult c, c, n\n    %ret c\n%endfunc\n%func main()\n    %int a\n    %int[] list, 3\n    %input a\n    %call a, fact(a)\n    %mov [list + 2], a\n    %mov a, [list + 2]\n    %call a, abs(a)\n    %out a\n    %ret 3\n%endfunc\n";
+        return crate::parse_ir(code, &HostRegistry::with_builtins()).ok().unwrap();
+    }
+
+    #[test]
+    fn bytecode_round_trip() {
+        let program = program();
+        let bytes = save(&program);
+        assert!(&bytes[..4] == b"TTBC" && bytes[4..6] == [1, 0]);
+        let loaded = load(&bytes).ok().unwrap();
+        assert!(save(&loaded) == bytes);
+        assert!(loaded.functions[1].ir_line(3) == program.functions[1].ir_line(3));
+
+        let mut host = HostRegistry::with_builtins();
+        let result = crate::run(&loaded, "5", &VmConfig::default(), &mut host);
+        assert!(matches!(result.result, Ok(3)) && result.output == vec![120]);
+    }
+
+    #[test]
+    fn bytecode_load_errors() {
+        let bytes = save(&program());
+        assert!(matches!(load(b"%func main()\n"), Err(LoadError::NotBytecode)));
+
+        let mut newer = bytes.clone();
+        newer[4] = 2;
+        assert!(matches!(load(&newer), Err(LoadError::UnsupportedVersion(2))));
+        assert!(matches!(load(&bytes[..bytes.len() - 1]), Err(LoadError::Truncated)));
+
+        let mut corrupted = bytes.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 1;
+        assert!(matches!(load(&corrupted), Err(LoadError::Checksum)));
+
+        // a correct checksum does not make a bad slot or jump acceptable.
+        let mut bad = program();
+        bad.functions[1].body[7] = Bytecode::Out(Op::Var(9));
+        let e = load(&save(&bad)).err().unwrap();
+        assert!(e.to_string() == "invalid bytecode. function 'main': integer slot 9 out of range.");
+
+        let mut bad = program();
+        bad.functions[0].body[2] = Bytecode::BranchIfn(Op::Var(1), 40);
+        let e = load(&save(&bad)).err().unwrap();
+        assert!(e.to_string() == "invalid bytecode. function 'fact': jump target 40 out of range.");
+
+        // the variable table is checked before anything is sized by it.
+        let mut bad = program();
+        bad.functions[1].variables.insert(String::from("a"), VariableType::IntVar(5));
+        let e = load(&save(&bad)).err().unwrap();
+        assert!(e.to_string() == "invalid bytecode. function 'main': integer slot 5 out of range.");
+
+        let mut bad = program();
+        bad.functions[1].int_slots = 0xfffffff0;
+        let e = load(&save(&bad)).err().unwrap();
+        assert!(e.to_string() == "invalid bytecode. function 'main': 4294967280 integer and 1 array slots for 1 integer and 1 array variables.");
+    }
+}
@@ -0,0 +1,430 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::*;
+use crate::diagnostics::Diagnostic;
+
+// lowers the AST into the '%func ... %endfunc' IR understood by interpreter::execute_ir.
+//
+// expressions are flattened into temporaries ('_temp0', '_temp1', ...), loops and ifs
+// become ':label's with '%branch_ifn' / '%jmp', arrays are accessed with '[arr + i]'.
+// source variables keep their names unless two scopes of one function declare the same
+// name, in which case the later one gets a suffix ('j_1') since IR functions are flat.
+
+// the .tt line every line of the generated IR came from, so runtime errors can point at the source.
+pub struct SourceMap {
+    // indexed by IR line - 1, 0 for lines without a .tt origin.
+    lines: Vec<usize>,
+}
+
+impl SourceMap {
+    pub fn source_line(&self, ir_line: usize) -> Option<usize> {
+        match self.lines.get(ir_line.wrapping_sub(1)) {
+        Some(0) | None => None,
+        Some(line) => Some(*line),
+        }
+    }
+}
+
+// the program must have passed semantic::check_program, errors here are compiler bugs (E0401).
+pub fn generate_ir(program: &Program) -> Result<(String, SourceMap), Diagnostic> {
+    let mut code = String::new();
+    let mut lines: Vec<usize> = vec![];
+    for function in &program.functions {
+        let gen = generate_function(function)?;
+        code.push_str(&gen.code);
+        lines.extend(gen.lines);
+        code.push('\n');
+        lines.push(0);
+    }
+    return Ok((code, SourceMap { lines }));
+}
+
+struct FunctionGen {
+    code: String,
+
+    // .tt line of every line in code, and the line of the statement being generated.
+    lines: Vec<usize>,
+    line: usize,
+
+    scopes: Vec<HashMap<String, String>>,
+    used: HashSet<String>,
+    temp_count: usize,
+    label_count: usize,
+
+    // (continue label, break label) of every loop we are inside of.
+    loops: Vec<(String, String)>,
+}
+
+fn generate_function(function: &Function) -> Result<FunctionGen, Diagnostic> {
+    let mut gen = FunctionGen {
+        code: String::new(),
+        lines: vec![],
+        line: function.span.line,
+        scopes: vec![HashMap::new()],
+        used: HashSet::new(),
+        temp_count: 0,
+        label_count: 0,
+        loops: vec![],
+    };
+
+    let mut params: Vec<String> = vec![];
+    for param in &function.params {
+        let name = gen.declare(&param.name.name);
+        params.push(format!("%int {name}"));
+    }
+    gen.code.push_str(&format!("%func {}({})\n", function.name.name, params.join(", ")));
+    gen.lines.push(gen.line);
+
+    gen.block(&function.body)?;
+
+    gen.code.push_str("%endfunc\n");
+    gen.lines.push(gen.line);
+    return Ok(gen);
+}
+
+impl FunctionGen {
+    fn emit(&mut self, instruction: String) {
+        self.code.push_str("    ");
+        self.code.push_str(&instruction);
+        self.code.push('\n');
+        self.lines.push(self.line);
+    }
+
+    fn emit_label(&mut self, label: &str) {
+        self.code.push_str(label);
+        self.code.push('\n');
+        self.lines.push(self.line);
+    }
+
+    // picks an IR name for a variable that is unique inside the function.
+    fn unique_name(&mut self, base: &str) -> String {
+        let mut name = String::from(base);
+        let mut suffix = 1;
+        while self.used.contains(&name) {
+            name = format!("{base}_{suffix}");
+            suffix += 1;
+        }
+        self.used.insert(name.clone());
+        name
+    }
+
+    fn declare(&mut self, name: &str) -> String {
+        let ir_name = self.unique_name(name);
+        self.scopes.last_mut().unwrap().insert(String::from(name), ir_name.clone());
+        ir_name
+    }
+
+    fn lookup(&self, name: &str) -> String {
+        for scope in self.scopes.iter().rev() {
+            if let Some(ir_name) = scope.get(name) {
+                return ir_name.clone();
+            }
+        }
+        // undeclared variables are left for the IR parser to report.
+        String::from(name)
+    }
+
+    fn temp(&mut self) -> String {
+        let name = self.unique_name(&format!("_temp{}", self.temp_count));
+        self.temp_count += 1;
+        self.emit(format!("%int {name}"));
+        name
+    }
+
+    fn label(&mut self, base: &str) -> String {
+        format!(":{base}{}", self.label_count)
+    }
+
+    fn block(&mut self, block: &Block) -> Result<(), Diagnostic> {
+        self.scopes.push(HashMap::new());
+        for stmt in &block.stmts {
+            self.statement(stmt)?;
+        }
+        self.scopes.pop();
+        Ok(())
+    }
+
+    fn statement(&mut self, stmt: &Stmt) -> Result<(), Diagnostic> {
+        // the jumps and labels after a loop or if body belong to the loop or if itself.
+        let outer = self.line;
+        self.line = stmt.span.line;
+        match &stmt.kind {
+
+        StmtKind::Declare { ty, name, init } => {
+            // the initializer still sees the variable this one shadows, 'int i = i + 1;'.
+            let value = init.as_ref().map(|init| self.expression(init));
+            let ir_name = self.declare(&name.name);
+            match ty {
+            VarType::Int => self.emit(format!("%int {ir_name}")),
+            VarType::Array(len) => self.emit(format!("%int[] {ir_name}, {len}")),
+            }
+            if let Some(value) = value {
+                self.emit(format!("%mov {ir_name}, {value}"));
+            }
+        }
+
+        StmtKind::Assign { target, value } => {
+            let name = self.lookup(&target.name.name);
+            match &target.index {
+            None => {
+                let value = self.expression(value);
+                self.emit(format!("%mov {name}, {value}"));
+            }
+            Some(index) => {
+                let index = self.expression(index);
+                let value = self.expression(value);
+                self.emit(format!("%mov [{name} + {index}], {value}"));
+            }
+            }
+        }
+
+        StmtKind::While { cond, body } => {
+            let begin = self.label("loopbegin");
+            let end = self.label("endloop");
+            self.label_count += 1;
+
+            self.emit_label(&begin);
+            let cond = self.condition(cond);
+            self.emit(format!("%branch_ifn {cond}, {end}"));
+            self.loops.push((begin.clone(), end.clone()));
+            self.block(body)?;
+            self.loops.pop();
+            self.emit(format!("%jmp {begin}"));
+            self.emit_label(&end);
+        }
+
+        StmtKind::If { cond, then_block, else_block } => {
+            let else_label = self.label("else");
+            let end = self.label("endif");
+            self.label_count += 1;
+
+            let cond = self.condition(cond);
+            match else_block {
+            None => {
+                self.emit(format!("%branch_ifn {cond}, {end}"));
+                self.block(then_block)?;
+            }
+            Some(else_block) => {
+                self.emit(format!("%branch_ifn {cond}, {else_label}"));
+                self.block(then_block)?;
+                self.emit(format!("%jmp {end}"));
+                self.emit_label(&else_label);
+                self.block(else_block)?;
+            }
+            }
+            self.emit_label(&end);
+        }
+
+        StmtKind::Break => {
+            match self.loops.last() {
+            Some((_, end)) => {
+                let end = end.clone();
+                self.emit(format!("%jmp {end}"));
+            }
+            // semantic::check_program reports this as E0305, reaching it here is a compiler bug.
+            None => return Err(Diagnostic::new("E0401", stmt.span, String::from("internal codegen error, 'break' outside of a loop got past the semantic check."))),
+            }
+        }
+
+        StmtKind::Continue => {
+            match self.loops.last() {
+            Some((begin, _)) => {
+                let begin = begin.clone();
+                self.emit(format!("%jmp {begin}"));
+            }
+            // see 'break' above.
+            None => return Err(Diagnostic::new("E0401", stmt.span, String::from("internal codegen error, 'continue' outside of a loop got past the semantic check."))),
+            }
+        }
+
+        StmtKind::Print(expr) => {
+            let value = self.expression(expr);
+            self.emit(format!("%out {value}"));
+        }
+
+        StmtKind::Read(place) => {
+            let name = self.lookup(&place.name.name);
+            match &place.index {
+            None => self.emit(format!("%input {name}")),
+            Some(index) => {
+                let index = self.expression(index);
+                let temp = self.temp();
+                self.emit(format!("%input {temp}"));
+                self.emit(format!("%mov [{name} + {index}], {temp}"));
+            }
+            }
+        }
+
+        StmtKind::Return(expr) => {
+            let value = self.expression(expr);
+            self.emit(format!("%ret {value}"));
+        }
+
+        StmtKind::Expr(expr) => {
+            self.expression(expr);
+        }
+
+        }
+        self.line = outer;
+        Ok(())
+    }
+
+    // branches need a value that is exactly 0 or 1, so anything that is not
+    // already a comparison is compared against zero.
+    fn condition(&mut self, cond: &Expr) -> String {
+        let value = self.expression(cond);
+        if let ExprKind::Binary { op, .. } = &cond.kind {
+            if is_comparison(*op) {
+                return value;
+            }
+        }
+        let temp = self.temp();
+        self.emit(format!("%neq {temp}, {value}, 0"));
+        temp
+    }
+
+    // returns the operand holding the value of the expression, either a number or a variable.
+    fn expression(&mut self, expr: &Expr) -> String {
+        match &expr.kind {
+        ExprKind::Num(num) => num.to_string(),
+
+        ExprKind::Var(name) => self.lookup(name),
+
+        ExprKind::Index { array, index } => {
+            let name = self.lookup(&array.name);
+            let index = self.expression(index);
+            let temp = self.temp();
+            self.emit(format!("%mov {temp}, [{name} + {index}]"));
+            temp
+        }
+
+        ExprKind::Call { name, args } => {
+            let mut values: Vec<String> = vec![];
+            for arg in args {
+                values.push(self.expression(arg));
+            }
+            let temp = self.temp();
+            self.emit(format!("%call {temp}, {}({})", name.name, values.join(", ")));
+            temp
+        }
+
+        ExprKind::Unary { op: UnaryOp::Neg, expr } => {
+            // the IR accepts signed literals, so negated constants need no temporary.
+            if let ExprKind::Num(num) = expr.kind {
+                return (-(num as i64)).to_string();
+            }
+            let value = self.expression(expr);
+            let temp = self.temp();
+            self.emit(format!("%sub {temp}, 0, {value}"));
+            temp
+        }
+
+        ExprKind::Binary { op, lhs, rhs } => {
+            let lhs = self.expression(lhs);
+            let rhs = self.expression(rhs);
+            let temp = self.temp();
+            self.emit(format!("{} {temp}, {lhs}, {rhs}", opcode(*op)));
+            temp
+        }
+
+        }
+    }
+}
+
+fn opcode(op: BinaryOp) -> &'static str {
+    match op {
+    BinaryOp::Add => "%add",
+    BinaryOp::Sub => "%sub",
+    BinaryOp::Mul => "%mult",
+    BinaryOp::Div => "%div",
+    BinaryOp::Mod => "%mod",
+    BinaryOp::Less => "%lt",
+    BinaryOp::LessEqual => "%le",
+    BinaryOp::Greater => "%gt",
+    BinaryOp::GreaterEqual => "%ge",
+    BinaryOp::Equal => "%eq",
+    BinaryOp::NotEqual => "%neq",
+    }
+}
+
+fn is_comparison(op: BinaryOp) -> bool {
+    !matches!(op, BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod)
+}
+
+#[cfg(test)]
+mod codegen_tests {
+    use crate::codegen::*;
+    use crate::lexer::lex;
+    use crate::parser::parse_program;
+
+    fn generate(code: &str) -> String {
+        let program = parse_program(&lex(code).ok().unwrap()).ok().unwrap();
+        generate_ir(&program).ok().unwrap().0
+    }
+
+    #[test]
+    fn codegen_expressions() {
+        let ir = generate("func add(int a, int b) { return a + b * 2; }");
+        let expected = "%func add(%int a, %int b)\n    %int _temp0\n    %mult _temp0, b, 2\n    %int _temp1\n    %add _temp1, a, _temp0\n    %ret _temp1\n%endfunc\n\n";
+        assert!(ir == expected);
+
+        let ir = generate("func main() { int[4] a; a[1] = -a[0]; print(f(a[1], 3)); }");
+        assert!(ir.contains("%mov _temp0, [a + 0]\n"));
+        assert!(ir.contains("%sub _temp1, 0, _temp0\n"));
+        assert!(ir.contains("%mov [a + 1], _temp1\n"));
+        assert!(ir.contains("%call _temp3, f(_temp2, 3)\n"));
+        assert!(ir.contains("%out _temp3\n"));
+
+        let ir = generate("func main() { int x = -5; print(x * -(-2)); }");
+        assert!(ir.contains("%mov x, -5\n"));
+        assert!(ir.contains("%sub _temp0, 0, -2\n"));
+    }
+
+    #[test]
+    fn codegen_control_flow() {
+        let ir = generate("func main() { int i = 0; while i < 3 { if i == 1 { break; } else { int i = 2; } i = i + 1; } }");
+        assert!(ir.contains(":loopbegin0\n"));
+        assert!(ir.contains("%branch_ifn _temp0, :endloop0\n"));
+        assert!(ir.contains("%branch_ifn _temp1, :else1\n"));
+        assert!(ir.contains("%jmp :endloop0\n"));
+        assert!(ir.contains("%jmp :endif1\n"));
+        // the inner 'i' is a different variable from the outer one.
+        assert!(ir.contains("%int i_1\n    %mov i_1, 2\n"));
+        assert!(ir.contains("%add _temp2, i, 1\n    %mov i, _temp2\n"));
+
+        let program = parse_program(&lex("func main() { break; }").ok().unwrap()).ok().unwrap();
+        assert!(generate_ir(&program).err().unwrap().message.starts_with("internal codegen error"));
+    }
+
+    #[test]
+    fn codegen_shadowed_initializer() {
+        // the initializer reads the outer 'i', the new one only exists after it.
+        let code = "func main() { int i = 5; if i > 0 { int i = i + 1; print(i); } print(i); }";
+        let ir = generate(code);
+        assert!(ir.contains("%add _temp1, i, 1\n    %int i_1\n    %mov i_1, _temp1\n"));
+
+        let mut host = crate::host::HostRegistry::new();
+        let program = crate::compile(code, &host).ok().unwrap();
+        let result = crate::run(&program, "", &crate::interpreter::VmConfig::default(), &mut host);
+        assert!(result.output == vec![6, 5]);
+    }
+
+    #[test]
+    fn codegen_source_map() {
+        let code = "func main() {\n    int i = 0;\n    while i < 3 {\n        print(i);\n        i = i + 1;\n    }\n}\n";
+        let program = parse_program(&lex(code).ok().unwrap()).ok().unwrap();
+        let (ir, map) = generate_ir(&program).ok().unwrap();
+        let ir_lines: Vec<&str> = ir.lines().collect();
+        assert!(map.lines.len() == ir_lines.len());
+
+        let line_of = |text: &str| map.source_line(ir_lines.iter().position(|l| l.trim() == text).unwrap() + 1);
+        assert!(line_of("%func main()") == Some(1));
+        assert!(line_of("%mov i, 0") == Some(2));
+        assert!(line_of(":loopbegin0") == Some(3));
+        assert!(line_of("%out i") == Some(4));
+        assert!(line_of("%mov i, _temp1") == Some(5));
+        assert!(line_of("%jmp :loopbegin0") == Some(3));
+        assert!(map.source_line(ir_lines.len()).is_none());
+        assert!(map.source_line(0).is_none());
+    }
+}
@@ -0,0 +1,341 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::codegen::SourceMap;
+use crate::interpreter::{HookAction, VmHook, VmState};
+
+// which IR instructions ran, and which way every %branch_if / %branch_ifn went.
+//
+// coverage is kept per IR line, every instruction has a line of its own. the data file
+// lists every instruction line, executed or not, so listings can be made from it alone:
+//
+//     tt-coverage 1
+//     program 8c1f0d3a5e7b2f64
+//     <IR line> <hits>
+//     <IR line> <hits> <jumped> <fell through>     (branches)
+//
+// the program line is a fingerprint of the IR text, so data from several runs of the
+// same program can be merged while data from another program is refused.
+
+const HEADER: &str = "tt-coverage 1";
+
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct LineCoverage {
+    pub hits: u64,
+    // how often a branch jumped and how often it fell through, None for other instructions.
+    pub branch: Option<(u64, u64)>,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Coverage {
+    program: u64,
+    lines: BTreeMap<usize, LineCoverage>,
+}
+
+// FNV-1a, stable across platforms and rust versions unlike the std hasher.
+fn fingerprint(ir: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in ir.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+impl Coverage {
+    // coverage for the program parsed from 'ir'. its instructions are learnt from the run.
+    pub fn new(ir: &str) -> Coverage {
+        Coverage { program: fingerprint(ir), lines: BTreeMap::new() }
+    }
+
+    // whether the coverage was recorded for the program parsed from 'ir'.
+    pub fn is_for(&self, ir: &str) -> bool {
+        self.program == fingerprint(ir)
+    }
+
+    pub fn line(&self, ir_line: usize) -> Option<LineCoverage> {
+        self.lines.get(&ir_line).copied()
+    }
+
+    // (covered, total) instructions and branch outcomes.
+    pub fn totals(&self) -> ((usize, usize), (usize, usize)) {
+        let instructions = (self.lines.values().filter(|l| l.hits > 0).count(), self.lines.len());
+        let mut branches = (0, 0);
+        for (jumped, fell) in self.lines.values().filter_map(|l| l.branch) {
+            branches.0 += (jumped > 0) as usize + (fell > 0) as usize;
+            branches.1 += 2;
+        }
+        (instructions, branches)
+    }
+
+    pub fn to_data(&self) -> String {
+        let mut out = format!("{HEADER}\nprogram {:016x}\n", self.program);
+        for (line, coverage) in &self.lines {
+            let _ = match coverage.branch {
+            Some((jumped, fell)) => writeln!(out, "{line} {} {jumped} {fell}", coverage.hits),
+            None => writeln!(out, "{line} {}", coverage.hits),
+            };
+        }
+        out
+    }
+
+    pub fn from_data(data: &str) -> Result<Coverage, String> {
+        let mut lines = data.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(String::from("not a coverage data file."));
+        }
+        let program = lines.next()
+            .and_then(|l| l.strip_prefix("program "))
+            .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+            .ok_or_else(|| String::from("the coverage data has no program fingerprint."))?;
+
+        let mut coverage = Coverage { program, lines: BTreeMap::new() };
+        for (n, text) in lines.enumerate() {
+            let numbers: Option<Vec<u64>> = text.split_whitespace().map(|n| n.parse::<u64>().ok()).collect();
+            let line = match numbers.as_deref() {
+            Some([line, hits]) => (*line, LineCoverage { hits: *hits, branch: None }),
+            Some([line, hits, jumped, fell]) => (*line, LineCoverage { hits: *hits, branch: Some((*jumped, *fell)) }),
+            _ => return Err(format!("invalid coverage data on line {}.", n + 3)),
+            };
+            coverage.lines.insert(line.0 as usize, line.1);
+        }
+        Ok(coverage)
+    }
+
+    // adds the counts of another run of the same program.
+    pub fn merge(&mut self, other: &Coverage) -> Result<(), String> {
+        if other.program != self.program {
+            return Err(String::from("the coverage data is for a different program, or another version of it."));
+        }
+        for (line, theirs) in &other.lines {
+            let ours = self.lines.entry(*line).or_default();
+            ours.hits += theirs.hits;
+            ours.branch = match (ours.branch, theirs.branch) {
+            (Some((a, b)), Some((c, d))) => Some((a + c, b + d)),
+            (ours, theirs) => ours.or(theirs),
+            };
+        }
+        Ok(())
+    }
+
+    // the IR with the coverage of every instruction.
+    pub fn ir_listing(&self, ir: &str) -> Listing {
+        let rows = ir.lines().enumerate().map(|(i, text)| {
+            let mut row = Row { text: String::from(text), ..Row::default() };
+            if let Some(line) = self.line(i + 1) {
+                row.add(&line);
+            }
+            row
+        }).collect();
+        Listing { rows }
+    }
+
+    // the .tt source, each line with the coverage of the IR instructions generated for it.
+    pub fn source_listing(&self, source: &str, source_map: &SourceMap) -> Listing {
+        let mut rows: Vec<Row> = source.lines().map(|text| Row { text: String::from(text), ..Row::default() }).collect();
+        for (ir_line, line) in &self.lines {
+            if let Some(row) = source_map.source_line(*ir_line).and_then(|l| rows.get_mut(l - 1)) {
+                row.add(line);
+            }
+        }
+        Listing { rows }
+    }
+}
+
+impl VmHook for Coverage {
+    fn before_instruction(&mut self, state: &VmState) -> HookAction {
+        // the first instruction brings in every instruction line of the program, executed or not.
+        if self.lines.is_empty() {
+            for function in state.functions() {
+                for index in 0..function.len() {
+                    let branch = if function.is_branch(index) { Some((0, 0)) } else { None };
+                    self.lines.insert(function.ir_line(index), LineCoverage { hits: 0, branch });
+                }
+            }
+        }
+
+        // %endfunc is left out, a function that always returns with %ret never reaches it.
+        if let Some(line) = self.lines.get_mut(&state.ir_line()) {
+            line.hits += 1;
+            if let (Some((jumped, fell)), Some(jumps)) = (line.branch.as_mut(), state.branch()) {
+                if jumps { *jumped += 1 } else { *fell += 1 }
+            }
+        }
+        HookAction::Continue
+    }
+}
+
+// one line of a listing, with the coverage of the instructions on it.
+#[derive(Default)]
+struct Row {
+    text: String,
+    // None for lines without instructions.
+    hits: Option<u64>,
+    instructions: (usize, usize),
+    branches: (usize, usize),
+    // jumped and fell through, added up over the branches on the line.
+    outcomes: (u64, u64),
+}
+
+impl Row {
+    fn add(&mut self, line: &LineCoverage) {
+        self.hits = Some(self.hits.unwrap_or(0).max(line.hits));
+        self.instructions.0 += (line.hits > 0) as usize;
+        self.instructions.1 += 1;
+        if let Some((jumped, fell)) = line.branch {
+            self.branches.0 += (jumped > 0) as usize + (fell > 0) as usize;
+            self.branches.1 += 2;
+            self.outcomes.0 += jumped;
+            self.outcomes.1 += fell;
+        }
+    }
+
+    fn partial(&self) -> bool {
+        self.instructions.0 < self.instructions.1 || self.branches.0 < self.branches.1
+    }
+
+    // which instructions on the line never ran and how its branches went.
+    fn note(&self) -> String {
+        let mut notes = vec![];
+        if self.instructions.0 < self.instructions.1 && self.instructions.0 > 0 {
+            notes.push(format!("{} of {} instructions ran", self.instructions.0, self.instructions.1));
+        }
+        if self.branches.1 > 0 && self.instructions.0 > 0 {
+            notes.push(format!("jumped {}, fell through {}", self.outcomes.0, self.outcomes.1));
+        }
+        notes.join(", ")
+    }
+
+    // the css class in the html listing.
+    fn class(&self) -> &'static str {
+        match self.hits {
+        None => "none",
+        Some(0) => "missed",
+        Some(_) if self.partial() => "partial",
+        Some(_) => "covered",
+        }
+    }
+}
+
+pub struct Listing {
+    rows: Vec<Row>,
+}
+
+impl Listing {
+    fn summary(&self) -> String {
+        let mut instructions = (0, 0);
+        let mut branches = (0, 0);
+        for row in &self.rows {
+            instructions = (instructions.0 + row.instructions.0, instructions.1 + row.instructions.1);
+            branches = (branches.0 + row.branches.0, branches.1 + row.branches.1);
+        }
+        let percent = |(covered, total): (usize, usize)| if total == 0 { 100.0 } else { covered as f64 * 100.0 / total as f64 };
+        format!("instructions {}/{} ({:.1}%), branch outcomes {}/{} ({:.1}%)",
+            instructions.0, instructions.1, percent(instructions), branches.0, branches.1, percent(branches))
+    }
+
+    // gcov style: the hits, '#####' for lines that never ran, and a '!' for lines
+    // where some instructions or branch outcomes never ran.
+    pub fn text(&self) -> String {
+        let mut out = format!("{}\n\n", self.summary());
+        for (i, row) in self.rows.iter().enumerate() {
+            let hits = match row.hits {
+            None => String::from("-"),
+            Some(0) => String::from("#####"),
+            Some(hits) => hits.to_string(),
+            };
+            let mark = if row.hits.is_some_and(|h| h > 0) && row.partial() { '!' } else { ' ' };
+            let note = row.note();
+            let note = if note.is_empty() { note } else { format!("    [{note}]") };
+            let _ = writeln!(out, "{hits:>9}{mark} {:>4}  {}{note}", i + 1, row.text);
+        }
+        out
+    }
+
+    pub fn html(&self, title: &str) -> String {
+        let mut out = format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>coverage of {}</title>\n", escape(title));
+        out.push_str("<style>\n\
+            body { font-family: sans-serif; }\n\
+            table { border-collapse: collapse; font-family: monospace; }\n\
+            td { padding: 0 8px; white-space: pre; }\n\
+            td.hits, td.line { text-align: right; color: #666; }\n\
+            tr.covered td.code { background: #dfd; }\n\
+            tr.partial td.code { background: #ffc; }\n\
+            tr.missed td.code { background: #fdd; }\n\
+            </style>\n</head>\n<body>\n");
+        let _ = writeln!(out, "<h1>coverage of {}</h1>\n<p>{}</p>\n<table>", escape(title), self.summary());
+        for (i, row) in self.rows.iter().enumerate() {
+            let hits = row.hits.map_or(String::new(), |h| h.to_string());
+            let note = row.note();
+            let note = if note.is_empty() { note } else { format!(" title=\"{}\"", escape(&note)) };
+            let _ = writeln!(out, "<tr class=\"{}\"{note}><td class=\"hits\">{hits}</td><td class=\"line\">{}</td><td class=\"code\">{}</td></tr>",
+                row.class(), i + 1, escape(&row.text));
+        }
+        out.push_str("</table>\n</body>\n</html>\n");
+        out
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod coverage_tests {
+    use crate::coverage::*;
+    use crate::host::HostRegistry;
+    use crate::interpreter::VmConfig;
+    use crate::vm_io::*;
+
+    fn run(ir: &str, input: &str, coverage: &mut Coverage) {
+        let mut host = HostRegistry::new();
+        let program = crate::parse_ir(ir, &host).ok().unwrap();
+        let (mut input, mut output, mut reporter) = (MemoryInput::new(input), MemoryOutput::default(), MemoryReporter::default());
+        let mut io = VmIo { input: &mut input, output: &mut output, reporter: &mut reporter };
+        program.run_with_hook(&VmConfig::default(), &mut io, &mut host, coverage).ok().unwrap();
+    }
+
+    #[test]
+    fn coverage_runs() {
+        let ir = "%func main()\n    %int a\n    %int c\n    %input a\n    %lt c, a, 0\n    %branch_if c, :negative\n    %ret a\n:negative\n    %ret 0\n%endfunc\n";
+        let mut first = Coverage::new(ir);
+        run(ir, "5", &mut first);
+        assert!(first.line(6) == Some(LineCoverage { hits: 1, branch: Some((0, 1)) }));
+        assert!(first.line(8) == Some(LineCoverage { hits: 0, branch: None }));
+        assert!(first.line(1).is_none() && first.line(10).is_none());
+        assert!(first.totals() == ((6, 8), (1, 2)));
+
+        let text = first.ir_listing(ir).text();
+        assert!(text.starts_with("instructions 6/8 (75.0%), branch outcomes 1/2 (50.0%)\n\n        -     1  %func main()\n"));
+        assert!(text.contains("        1!    6      %branch_if c, :negative    [jumped 0, fell through 1]\n"));
+        assert!(text.contains("    #####     8  :negative\n"));
+
+        // a second run through the data file takes the other branch.
+        let mut second = Coverage::new(ir);
+        run(ir, "-5", &mut second);
+        let mut merged = Coverage::from_data(&first.to_data()).ok().unwrap();
+        merged.merge(&second).ok().unwrap();
+        assert!(merged.line(6) == Some(LineCoverage { hits: 2, branch: Some((1, 1)) }));
+        assert!(merged.totals() == ((8, 8), (2, 2)));
+        assert!(Coverage::from_data(&merged.to_data()).ok().unwrap() == merged);
+
+        assert!(merged.is_for(ir) && merged.merge(&Coverage::new("%func main()\n%endfunc\n")).is_err());
+        assert!(Coverage::from_data("tt-coverage 1\nprogram 00\n3 x\n").is_err());
+
+        let html = merged.ir_listing(ir).html("a<b>.ir");
+        assert!(html.contains("<title>coverage of a&lt;b&gt;.ir</title>"));
+        assert!(html.contains("<tr class=\"covered\" title=\"jumped 1, fell through 1\"><td class=\"hits\">2</td><td class=\"line\">6</td>"));
+    }
+
+    #[test]
+    fn coverage_source_listing() {
+        let source = "func main() {\n    int a;\n    read(a);\n    if a < 0 {\n        print(0);\n    }\n    return a;\n}\n";
+        let (ir, source_map) = crate::compile_to_ir(source, &HostRegistry::new()).ok().unwrap();
+        let mut coverage = Coverage::new(&ir);
+        run(&ir, "5", &mut coverage);
+        let text = coverage.source_listing(source, &source_map).text();
+        assert!(text.contains("        1!    4      if a < 0 {    [jumped 1, fell through 0]\n"));
+        assert!(text.contains("    #####     5          print(0);\n"));
+        assert!(text.contains("        1     7      return a;\n"));
+    }
+}
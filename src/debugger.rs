@@ -0,0 +1,350 @@
+use std::io::Write;
+
+use crate::interpreter::{HookAction, Local, VmHook, VmState};
+use crate::vm_io::InputSource;
+
+// an interactive debugger for IR programs, driven through a command prompt.
+// it stops before the first instruction of main, then wherever a breakpoint or
+// a step command says. when the commands run out, the program runs to its end.
+
+const HELP: &str = "\
+commands:
+    s, step                 run one instruction, entering calls
+    n, next                 run one instruction, stepping over calls
+    f, finish               run until the current function returns
+    c, continue             run until the next breakpoint
+    b, break <location>     stop at a function, a label (':label' or 'func:label') or an IR line
+    breakpoints             list the breakpoints
+    d, delete <n>           remove breakpoint n
+    p, print <name>         print a variable, 'a[i]' or 'a[i..j]' for part of an array
+    locals                  print every variable of the current function
+    bt, backtrace           print the active calls
+    l, list                 print the IR around the current instruction
+    q, quit                 stop the program
+an empty line repeats the last command.";
+
+// arrays longer than this are cut short when printed whole.
+const PRINT_LIMIT: usize = 32;
+
+enum Breakpoint {
+    Function(String),
+    // an optional function and the label name with its ':'.
+    Label(Option<String>, String),
+    Line(usize),
+}
+
+impl Breakpoint {
+    fn parse(location: &str, state: &VmState) -> Result<Breakpoint, String> {
+        if let Ok(line) = location.parse::<usize>() {
+            let valid = state.functions().iter().any(|f| (0..=f.len()).any(|i| f.ir_line(i) == line));
+            return if valid { Ok(Breakpoint::Line(line)) } else { Err(format!("IR line {line} has no instruction.")) };
+        }
+
+        match location.find(':') {
+        None => {
+            if state.functions().iter().any(|f| f.name() == location) {
+                Ok(Breakpoint::Function(String::from(location)))
+            } else {
+                Err(format!("there is no function '{location}'."))
+            }
+        }
+        Some(colon) => {
+            let (function, label) = location.split_at(colon);
+            let function = if function.is_empty() { None } else { Some(String::from(function)) };
+            let valid = state.functions().iter()
+                .filter(|f| function.as_deref().is_none_or(|name| f.name() == name))
+                .any(|f| f.label(label).is_some());
+            if valid {
+                Ok(Breakpoint::Label(function, String::from(label)))
+            } else {
+                Err(format!("there is no label '{location}'."))
+            }
+        }
+        }
+    }
+
+    fn hit(&self, state: &VmState) -> bool {
+        let function = state.function();
+        match self {
+        Breakpoint::Function(name) => function.name() == name && state.ip() == 0,
+        Breakpoint::Label(name, label) => {
+            name.as_deref().is_none_or(|name| function.name() == name) && function.label(label) == Some(state.ip())
+        }
+        Breakpoint::Line(line) => state.ir_line() == *line,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+        Breakpoint::Function(name) => format!("function '{name}'"),
+        Breakpoint::Label(Some(name), label) => format!("label '{name}{label}'"),
+        Breakpoint::Label(None, label) => format!("label '{label}'"),
+        Breakpoint::Line(line) => format!("IR line {line}"),
+        }
+    }
+}
+
+// when to stop next, besides breakpoints.
+enum Mode {
+    Step,
+    // stop once the call depth is at most this.
+    Next(usize),
+    // stop once the call depth is below this.
+    Finish(usize),
+    Continue,
+}
+
+pub struct Debugger<'a> {
+    commands: &'a mut dyn InputSource,
+    out: &'a mut dyn Write,
+    ir: Vec<String>,
+    breakpoints: Vec<Option<Breakpoint>>,
+    mode: Mode,
+    last_command: String,
+}
+
+impl<'a> Debugger<'a> {
+    // 'ir' is the program's IR text, for showing the lines being run.
+    pub fn new(ir: &str, commands: &'a mut dyn InputSource, out: &'a mut dyn Write) -> Debugger<'a> {
+        Debugger {
+            commands,
+            out,
+            ir: ir.lines().map(String::from).collect(),
+            breakpoints: vec![],
+            mode: Mode::Step,
+            last_command: String::new(),
+        }
+    }
+
+    fn should_stop(&self, state: &VmState) -> Option<String> {
+        if let Some(n) = self.breakpoints.iter().position(|b| b.as_ref().is_some_and(|b| b.hit(state))) {
+            return Some(format!("breakpoint {}, ", n + 1));
+        }
+        let stop = match self.mode {
+        Mode::Step => true,
+        Mode::Next(depth) => state.depth() <= depth,
+        Mode::Finish(depth) => state.depth() < depth,
+        Mode::Continue => false,
+        };
+        return if stop { Some(String::new()) } else { None };
+    }
+
+    fn show_line(&mut self, line: usize, current: bool) -> std::io::Result<()> {
+        let text = self.ir.get(line - 1).map_or("", |l| l.as_str());
+        let marker = if current { "=>" } else { "  " };
+        writeln!(self.out, "{marker} {line:4}  {text}")
+    }
+
+    fn show_location(&mut self, reason: &str, state: &VmState) -> std::io::Result<()> {
+        write!(self.out, "{reason}stopped in {}, IR line {}", state.function().name(), state.ir_line())?;
+        if let Some(line) = state.source_line() {
+            write!(self.out, ", .tt line {line}")?;
+        }
+        writeln!(self.out)?;
+        self.show_line(state.ir_line(), true)
+    }
+
+    fn print(&mut self, expr: &str, state: &VmState) -> std::io::Result<()> {
+        let (name, range) = match expr.split_once('[') {
+        None => (expr, None),
+        Some((name, rest)) => match rest.strip_suffix(']') {
+            Some(range) => (name, Some(range)),
+            None => return writeln!(self.out, "expected ']' after '{expr}'."),
+        },
+        };
+
+        let values = match (state.local(name), range) {
+        (None, _) => return writeln!(self.out, "there is no variable '{name}' in {}.", state.function().name()),
+        (Some(Local::Int(value)), None) => return writeln!(self.out, "{name} = {value}"),
+        (Some(Local::Int(_)), Some(_)) => return writeln!(self.out, "'{name}' is an integer, not an array."),
+        (Some(Local::Array(values)), _) => values,
+        };
+
+        let Some(range) = range else {
+            let shown = &values[..values.len().min(PRINT_LIMIT)];
+            let more = if shown.len() < values.len() { ", ..." } else { "" };
+            return writeln!(self.out, "{name} = {}{more}] (length {})", list(shown), values.len());
+        };
+
+        let bounds = match range.split_once("..") {
+        None => range.trim().parse::<usize>().ok().map(|i| (i, i + 1)),
+        Some((start, end)) => start.trim().parse::<usize>().ok().zip(end.trim().parse::<usize>().ok()),
+        };
+        match bounds {
+        Some((start, end)) if start < end && end <= values.len() => {
+            if end - start == 1 {
+                writeln!(self.out, "{name}[{start}] = {}", values[start])
+            } else {
+                writeln!(self.out, "{name}[{start}..{end}] = {}]", list(&values[start..end]))
+            }
+        }
+        _ => writeln!(self.out, "'{range}' is not a valid index range for '{name}' (length {}).", values.len()),
+        }
+    }
+
+    // runs commands until one resumes the program.
+    fn prompt(&mut self, state: &VmState) -> std::io::Result<HookAction> {
+        loop {
+            write!(self.out, "(debug) ")?;
+            self.out.flush()?;
+            let mut line = String::new();
+            if self.commands.read_line(&mut line)? == 0 {
+                // no more commands, let the program finish on its own.
+                writeln!(self.out)?;
+                self.breakpoints.clear();
+                self.mode = Mode::Continue;
+                return Ok(HookAction::Continue);
+            }
+
+            let mut line = line.trim().to_string();
+            if line.is_empty() {
+                line = self.last_command.clone();
+            }
+            self.last_command = line.clone();
+            let (command, arg) = match line.split_once(char::is_whitespace) {
+            Some((command, arg)) => (command, arg.trim()),
+            None => (line.as_str(), ""),
+            };
+
+            match command {
+            "" => {}
+            "s" | "step" => {
+                self.mode = Mode::Step;
+                return Ok(HookAction::Continue);
+            }
+            "n" | "next" => {
+                self.mode = Mode::Next(state.depth());
+                return Ok(HookAction::Continue);
+            }
+            "f" | "finish" => {
+                self.mode = Mode::Finish(state.depth());
+                return Ok(HookAction::Continue);
+            }
+            "c" | "continue" => {
+                self.mode = Mode::Continue;
+                return Ok(HookAction::Continue);
+            }
+            "q" | "quit" => return Ok(HookAction::Stop),
+
+            "b" | "break" => match Breakpoint::parse(arg, state) {
+                Ok(breakpoint) => {
+                    writeln!(self.out, "breakpoint {} at {}", self.breakpoints.len() + 1, breakpoint.describe())?;
+                    self.breakpoints.push(Some(breakpoint));
+                }
+                Err(message) => writeln!(self.out, "can not set a breakpoint, {message}")?,
+            },
+            "breakpoints" => {
+                let mut none = true;
+                for (n, breakpoint) in self.breakpoints.iter().enumerate() {
+                    if let Some(breakpoint) = breakpoint {
+                        writeln!(self.out, "{}: {}", n + 1, breakpoint.describe())?;
+                        none = false;
+                    }
+                }
+                if none {
+                    writeln!(self.out, "no breakpoints.")?;
+                }
+            }
+            "d" | "delete" => {
+                // numbers stay stable, deleting only empties the entry.
+                match arg.parse::<usize>().ok().and_then(|n| self.breakpoints.get_mut(n.wrapping_sub(1))) {
+                Some(entry) if entry.is_some() => *entry = None,
+                _ => writeln!(self.out, "there is no breakpoint '{arg}'.")?,
+                }
+            }
+
+            "p" | "print" if !arg.is_empty() => self.print(arg, state)?,
+            "locals" => {
+                for (name, _) in state.locals() {
+                    self.print(name, state)?;
+                }
+            }
+            "bt" | "backtrace" => {
+                for (n, entry) in state.backtrace().iter().enumerate() {
+                    write!(self.out, "#{n} {}, IR line {}", entry.function, entry.ir_line)?;
+                    if let Some(line) = entry.source_line {
+                        write!(self.out, ", .tt line {line}")?;
+                    }
+                    writeln!(self.out)?;
+                }
+            }
+            "l" | "list" => {
+                let current = state.ir_line();
+                for line in current.saturating_sub(3).max(1)..=(current + 3).min(self.ir.len()) {
+                    self.show_line(line, line == current)?;
+                }
+            }
+            "h" | "help" => writeln!(self.out, "{HELP}")?,
+            _ => writeln!(self.out, "unknown command '{line}'. type 'help' for the commands.")?,
+            }
+        }
+    }
+}
+
+impl VmHook for Debugger<'_> {
+    fn before_instruction(&mut self, state: &VmState) -> HookAction {
+        let Some(reason) = self.should_stop(state) else { return HookAction::Continue };
+        let result = self.show_location(&reason, state).and_then(|_| self.prompt(state));
+        // without a working terminal there is nobody left to debug for.
+        return result.unwrap_or(HookAction::Stop);
+    }
+}
+
+// the opening of a printed array, "[1, 2, 3".
+fn list(values: &[i32]) -> String {
+    let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+    format!("[{}", values.join(", "))
+}
+
+#[cfg(test)]
+mod debugger_tests {
+    use crate::debugger::*;
+    use crate::host::HostRegistry;
+    use crate::interpreter::{parse_program, RuntimeErrorKind, VmConfig};
+    use crate::vm_io::*;
+
+    const CODE: &str = "%func twice(%int x)\n    %int r\n    %mult r, x, 2\n    %ret r\n%endfunc\n%func main()\n    %int a\n    %int[] list, 4\n    %mov a, 5\n:again\n    %call a, twice(a)\n    %mov [list + 1], a\n    %out a\n%endfunc\n";
+
+    // runs CODE with the debugger reading 'commands', returns what it printed.
+    fn debug(commands: &str) -> (Result<i32, RuntimeErrorKind>, String) {
+        let mut host = HostRegistry::new();
+        let program = parse_program(CODE, &host).ok().unwrap();
+        let (mut input, mut output, mut reporter) = (MemoryInput::new(""), MemoryOutput::default(), MemoryReporter::default());
+        let mut io = VmIo { input: &mut input, output: &mut output, reporter: &mut reporter };
+        let mut commands = MemoryInput::new(commands);
+        let mut out: Vec<u8> = vec![];
+        let mut debugger = Debugger::new(CODE, &mut commands, &mut out);
+        let result = program.run_with_hook(&VmConfig::default(), &mut io, &mut host, &mut debugger).map_err(|e| e.kind);
+        return (result, String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn debugger_breakpoints() {
+        let (result, out) = debug("b twice\nb :again\nb 13\nb nothing\nc\nc\nbt\nf\nc\nc\n");
+        assert!(matches!(result, Ok(0)));
+        let stops: Vec<&str> = out.lines().filter(|l| l.contains("stopped in")).collect();
+        assert!(stops == vec![
+            "stopped in main, IR line 7",
+            "(debug) breakpoint 2, stopped in main, IR line 10",
+            "(debug) breakpoint 1, stopped in twice, IR line 2",
+            "(debug) stopped in main, IR line 12",
+            "(debug) breakpoint 3, stopped in main, IR line 13",
+        ]);
+        assert!(out.contains("(debug) breakpoint 2 at label ':again'\n"));
+        assert!(out.contains("can not set a breakpoint, there is no function 'nothing'."));
+        assert!(out.contains("#0 twice, IR line 2\n#1 main, IR line 11\n"));
+    }
+
+    #[test]
+    fn debugger_stepping() {
+        let (result, out) = debug("n\nn\nn\n\nn\np a\np list\np list[1]\np list[0..2]\np list[3..9]\nlocals\ns\nq\n");
+        assert!(matches!(result, Err(RuntimeErrorKind::Stopped)));
+
+        // 'next' steps over the call on line 11, an empty line repeats it.
+        assert!(out.contains("(debug) stopped in main, IR line 11\n=>   11      %call a, twice(a)\n(debug) stopped in main, IR line 12\n"));
+        assert!(!out.contains("stopped in twice"));
+        assert!(out.contains("(debug) a = 10\n(debug) list = [0, 0, 0, 0] (length 4)\n(debug) list[1] = 0\n(debug) list[0..2] = [0, 0]\n"));
+        assert!(out.contains("'3..9' is not a valid index range for 'list' (length 4)."));
+        assert!(out.contains("(debug) a = 10\nlist = [0, 0, 0, 0] (length 4)\n(debug) stopped in main, IR line 13\n"));
+    }
+}
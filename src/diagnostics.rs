@@ -0,0 +1,149 @@
+use std::fmt;
+
+use crate::lexer::Span;
+
+// compiler errors for .tt sources, rendered like:
+//
+//   error[E0302]: variable 'i' is already declared.
+//    --> examples/foo.tt:4:9
+//     |
+//   4 |     int i;
+//     |         ^
+//   note: previously declared here
+//    --> examples/foo.tt:2:9
+//     |
+//   2 |     int i;
+//     |         ^
+//
+// error codes:
+//   E01xx  lexer      E0101 invalid character, E0102 invalid number literal,
+//                     E0103 number literal out of range
+//   E02xx  parser     E0201 unexpected token, E0202 unclosed block,
+//                     E0203 invalid assignment target, E0204 invalid statement,
+//                     E0205 expression nested too deeply
+//   E03xx  semantic   E0301 undeclared variable, E0302 redeclared variable,
+//                     E0303 undeclared function, E0304 redeclared function,
+//                     E0305 break/continue outside of a loop, E0306 array used as an integer,
+//                     E0307 integer used as an array, E0308 wrong number of arguments,
+//                     E0309 missing main, E0310 main with parameters, E0311 invalid array size
+//   E04xx  codegen    E0401 internal codegen error
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub code: &'static str,
+    pub message: String,
+
+    // a span with line 0 means the error is not tied to a place in the file.
+    pub span: Span,
+    pub notes: Vec<Note>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Note {
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl Diagnostic {
+    pub fn new(code: &'static str, span: Span, message: String) -> Diagnostic {
+        Diagnostic { code, message, span, notes: vec![] }
+    }
+
+    pub fn with_note(mut self, span: Option<Span>, message: &str) -> Diagnostic {
+        self.notes.push(Note { message: String::from(message), span });
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.span.line == 0 {
+            write!(f, "error[{}]: {}", self.code, self.message)
+        } else {
+            write!(f, "error[{}] at line {}, column {}: {}", self.code, self.span.line, self.span.col, self.message)
+        }
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+// renders the diagnostic with the offending source lines underlined.
+pub fn render(filename: &str, source: &str, diagnostic: &Diagnostic) -> String {
+    let mut out = format!("error[{}]: {}\n", diagnostic.code, diagnostic.message);
+    if diagnostic.span.line != 0 {
+        out.push_str(&snippet(filename, source, diagnostic.span));
+    } else {
+        out.push_str(&format!(" --> {filename}\n"));
+    }
+
+    for note in &diagnostic.notes {
+        out.push_str(&format!("note: {}\n", note.message));
+        if let Some(span) = note.span {
+            out.push_str(&snippet(filename, source, span));
+        }
+    }
+    return out;
+}
+
+// renders every diagnostic followed by a summary line.
+pub fn render_all(filename: &str, source: &str, diagnostics: &[Diagnostic]) -> String {
+    let mut out = String::new();
+    for d in diagnostics {
+        out.push_str(&render(filename, source, d));
+        out.push('\n');
+    }
+    let plural = if diagnostics.len() == 1 { "" } else { "s" };
+    out.push_str(&format!("**{filename}: {} error{plural} found.\n", diagnostics.len()));
+    return out;
+}
+
+fn snippet(filename: &str, source: &str, span: Span) -> String {
+    let line_text = source.lines().nth(span.line - 1).unwrap_or("");
+    let number = span.line.to_string();
+    let gutter = " ".repeat(number.len());
+
+    // underline the part of the span on its first line, at least one character wide.
+    let line_start = source[..span.start.min(source.len())].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = line_start + line_text.len();
+    let end = span.end.clamp(span.start, line_end.max(span.start));
+    let width = source.get(span.start..end).map_or(0, |s| s.chars().count()).max(1);
+    let underline = format!("^{}", "~".repeat(width - 1));
+
+    let mut out = format!("{gutter}--> {filename}:{}:{}\n", span.line, span.col);
+    out.push_str(&format!("{gutter} |\n"));
+    out.push_str(&format!("{number} | {line_text}\n"));
+    out.push_str(&format!("{gutter} | {}{underline}\n", " ".repeat(span.col - 1)));
+    return out;
+}
+
+#[cfg(test)]
+mod diagnostics_tests {
+    use crate::diagnostics::*;
+
+    #[test]
+    fn render_snippet() {
+        let source = "func main() {\n    int abc;\n    int abc;\n}\n";
+        let first = Span { start: 22, end: 25, line: 2, col: 9 };
+        let second = Span { start: 35, end: 38, line: 3, col: 9 };
+        let d = Diagnostic::new("E0302", second, String::from("variable 'abc' is already declared."))
+            .with_note(Some(first), "previously declared here");
+
+        let expected = "\
+error[E0302]: variable 'abc' is already declared.
+ --> test.tt:3:9
+  |
+3 |     int abc;
+  |         ^~~
+note: previously declared here
+ --> test.tt:2:9
+  |
+2 |     int abc;
+  |         ^~~
+";
+        assert!(render("test.tt", source, &d) == expected);
+
+        // zero width spans still get a caret.
+        let d = Diagnostic::new("E0201", Span { start: 25, end: 25, line: 2, col: 12 }, String::from("expected ';'"));
+        assert!(render("test.tt", source, &d).ends_with("2 |     int abc;\n  |            ^\n"));
+    }
+}
@@ -0,0 +1,126 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Instant;
+
+// native rust functions that '%call' (and calls in .tt sources) can target like IR functions.
+//
+// a function defined in the program itself takes precedence over a host function with the
+// same name. programs only remember the host functions they call, by name and arity, so a
+// parsed program can be run with any registry that provides those, in any order.
+
+pub type HostFn = Box<dyn FnMut(&[i32]) -> Result<i32, String>>;
+
+pub struct HostFunction {
+    pub name: String,
+    pub arity: usize,
+    function: HostFn,
+}
+
+pub struct HostRegistry {
+    functions: Vec<HostFunction>,
+
+    // when the current run started, see start_run.
+    run_start: Rc<Cell<Instant>>,
+}
+
+impl Default for HostRegistry {
+    fn default() -> HostRegistry {
+        HostRegistry { functions: vec![], run_start: Rc::new(Cell::new(Instant::now())) }
+    }
+}
+
+impl HostRegistry {
+    pub fn new() -> HostRegistry {
+        HostRegistry::default()
+    }
+
+    // abs(x), rand_seeded(seed) and time_ms().
+    pub fn with_builtins() -> HostRegistry {
+        let mut registry = HostRegistry::new();
+        registry.register("abs", 1, |args| {
+            args[0].checked_abs().ok_or_else(|| format!("abs({}) does not fit in a 32-bit integer.", args[0]))
+        });
+        registry.register("rand_seeded", 1, |args| Ok(rand_seeded(args[0])));
+
+        // milliseconds since the current run started, wrapping after about 24 days.
+        let run_start = Rc::clone(&registry.run_start);
+        registry.register("time_ms", 0, move |_| Ok(run_start.get().elapsed().as_millis() as i32));
+        registry
+    }
+
+    // a later registration with the same name replaces the earlier one.
+    pub fn register(&mut self, name: &str, arity: usize, function: impl FnMut(&[i32]) -> Result<i32, String> + 'static) {
+        let entry = HostFunction { name: String::from(name), arity, function: Box::new(function) };
+        match self.find(name) {
+        Some(id) => self.functions[id] = entry,
+        None => self.functions.push(entry),
+        }
+    }
+
+    // called by the VM when a program starts running, a registry can be used for many runs.
+    pub fn start_run(&mut self) {
+        self.run_start.set(Instant::now());
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.functions.iter().position(|f| f.name == name)
+    }
+
+    pub fn functions(&self) -> impl Iterator<Item = &HostFunction> {
+        self.functions.iter()
+    }
+
+    pub fn get(&self, id: usize) -> &HostFunction {
+        &self.functions[id]
+    }
+
+    pub fn call(&mut self, id: usize, args: &[i32]) -> Result<i32, String> {
+        (self.functions[id].function)(args)
+    }
+}
+
+// a pseudo random, non-negative number that only depends on the seed (splitmix64),
+// so programs using it give the same output on every run and platform.
+fn rand_seeded(seed: i32) -> i32 {
+    let mut z = (seed as u32 as u64).wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^= z >> 31;
+    (z >> 33) as i32
+}
+
+#[cfg(test)]
+mod host_tests {
+    use crate::host::*;
+
+    #[test]
+    fn host_registry() {
+        let mut host = HostRegistry::with_builtins();
+        let abs = host.find("abs").unwrap();
+        assert!(host.get(abs).arity == 1);
+        assert!(host.call(abs, &[-5]) == Ok(5));
+        assert!(host.call(abs, &[i32::MIN]).is_err());
+
+        let rand = host.find("rand_seeded").unwrap();
+        let first = host.call(rand, &[42]).unwrap();
+        assert!(first >= 0 && host.call(rand, &[42]) == Ok(first) && host.call(rand, &[43]) != Ok(first));
+
+        let mut total = 0;
+        host.register("add_all", 3, move |args| {
+            total += args.iter().sum::<i32>();
+            Ok(total)
+        });
+        let add_all = host.find("add_all").unwrap();
+        assert!(host.call(add_all, &[1, 2, 3]) == Ok(6) && host.call(add_all, &[1, 1, 1]) == Ok(9));
+
+        // time_ms counts from the start of each run, not from when the registry was made.
+        let time_ms = host.find("time_ms").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert!(host.call(time_ms, &[]).unwrap() >= 20);
+        host.start_run();
+        assert!(host.call(time_ms, &[]).unwrap() < 20);
+
+        host.register("abs", 1, |_| Err(String::from("replaced")));
+        assert!(host.find("abs") == Some(abs) && host.call(abs, &[1]).is_err());
+    }
+}
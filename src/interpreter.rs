@@ -1,50 +1,185 @@
-pub fn execute_ir(code: &str) {
-    let tokens = lex_ir(code);
-    let bytecode = match parse_ir(&tokens, &mut 0) {
-    Ok(bytecode) => {
-        println!("Valid IR. Executing Generated Bytecode...");
-        bytecode
+#[derive(Debug)]
+pub enum ExecuteError {
+    Ir(IRError),
+    Runtime(RuntimeError),
+}
+
+// parses and runs the IR, reporting errors and the exit code as it goes.
+// source_map relates IR lines back to the .tt file when the IR was generated by codegen.
+// 'hook', when given, sees every instruction before it executes (see VmHook).
+pub fn execute_ir(code: &str, config: &VmConfig, source_map: Option<SourceMap>, io: &mut VmIo, host: &mut HostRegistry, hook: Option<&mut dyn VmHook>) -> Result<i32, ExecuteError> {
+    let program = match parse_program(code, host) {
+    Ok(program) => {
+        io.reporter.report("Valid IR. Executing Generated Bytecode...");
+        program.with_source_map(source_map)
     }
 
     Err(e) => {
-        println!("***Error. Invalid Bytecode.");
-        println!("------------------");
+        let mut listing = String::from("***Error. Invalid Bytecode.\n------------------\n");
         for (i, l) in code.lines().enumerate() {
-            println!("{:03}:  {}", i+1, l);
+            listing.push_str(&format!("{:03}:  {}\n", i+1, l));
             if i+1 == e.line {
+                if e.column != 0 {
+                    listing.push_str(&format!("      {}^\n", " ".repeat(e.column - 1)));
+                }
                 break;
             }
         }
-        println!("------------------");
-        println!("{e}");
-        return;
+        listing.push_str(&format!("------------------\n{e}"));
+        io.reporter.report(&listing);
+        return Err(ExecuteError::Ir(e));
     }
 
     };
 
-    let stdin = io::stdin();
-    run_program(&stdin, &bytecode);
+    return execute_program(&program, config, io, host, hook).map_err(ExecuteError::Runtime);
+}
+
+// runs an already parsed or loaded program, reporting the exit code or the runtime error.
+pub fn execute_program(program: &Program, config: &VmConfig, io: &mut VmIo, host: &mut HostRegistry, hook: Option<&mut dyn VmHook>) -> Result<i32, RuntimeError> {
+    match program.run_hooked(config, io, host, hook) {
+    Ok(n) => {
+        io.reporter.report(&format!("Run successful. Exit code {}", n));
+        return Ok(n);
+    }
+    Err(e) => {
+        io.reporter.report(&e.to_string());
+        return Err(e);
+    }
+    }
+}
+
+// a parsed IR file, ready to run as often as needed.
+pub struct Program {
+    pub(crate) functions: Vec<FunctionBytecode>,
+    pub(crate) source_map: Option<SourceMap>,
+
+    // name and arity of every host function the program calls, the ids in CallHost index this.
+    pub(crate) host_signature: Vec<(String, usize)>,
+}
+
+// calls that do not match a function in the IR are resolved against 'host'.
+pub fn parse_program(code: &str, host: &HostRegistry) -> Result<Program, IRError> {
+    let tokens = lex_ir(code)?;
+    let mut functions = parse_ir(&tokens, &mut 0, host)?;
+
+    // parse_ir leaves registry ids in CallHost. only the host functions actually called are
+    // kept, by name, so the program runs with any registry that provides them (see run).
+    let mut host_signature: Vec<(String, usize)> = vec![];
+    let mut ids: HashMap<usize, usize> = HashMap::new();
+    for function in &mut functions {
+        for instr in &mut function.body {
+            if let Bytecode::CallHost(_, host_id, _) = instr {
+                *host_id = *ids.entry(*host_id).or_insert_with(|| {
+                    let f = host.get(*host_id);
+                    host_signature.push((f.name.clone(), f.arity));
+                    host_signature.len() - 1
+                });
+            }
+        }
+    }
+    return Ok(Program { functions, source_map: None, host_signature });
 }
 
-use std::io;
+impl Program {
+    pub fn functions(&self) -> &[FunctionBytecode] {
+        &self.functions
+    }
+
+    pub fn with_source_map(mut self, source_map: Option<SourceMap>) -> Program {
+        self.source_map = source_map;
+        self
+    }
+
+    // runs main and returns its return value. 'host' has to provide every host function
+    // the program calls, with the same name and arity.
+    pub fn run(&self, config: &VmConfig, io: &mut VmIo, host: &mut HostRegistry) -> Result<i32, RuntimeError> {
+        return self.run_hooked(config, io, host, None);
+    }
+
+    // same as run, calling the hook before every instruction.
+    pub fn run_with_hook(&self, config: &VmConfig, io: &mut VmIo, host: &mut HostRegistry, hook: &mut dyn VmHook) -> Result<i32, RuntimeError> {
+        return self.run_hooked(config, io, host, Some(hook));
+    }
+
+    fn run_hooked(&self, config: &VmConfig, io: &mut VmIo, host: &mut HostRegistry, hook: Option<&mut dyn VmHook>) -> Result<i32, RuntimeError> {
+        let main = match self.functions.iter().find(|f| f.name == "main") {
+        Some(main) => main,
+        None => return Err(RuntimeError { kind: RuntimeErrorKind::MissingMain, backtrace: vec![] }),
+        };
+        // look the host functions up again by name, their ids may differ from the registry the
+        // program was parsed with.
+        let mut host_ids = vec![];
+        for (name, arity) in &self.host_signature {
+            match host.find(name) {
+            Some(id) if host.get(id).arity == *arity => host_ids.push(id),
+            _ => return Err(RuntimeError { kind: RuntimeErrorKind::HostMismatch { function: name.clone(), arity: *arity }, backtrace: vec![] }),
+            }
+        }
+
+        host.start_run();
+        let mut machine = Machine { calls: &self.functions, config, source_map: self.source_map.as_ref(), host, host_ids: &host_ids, hook };
+        let result = run_bytecode(io, &mut machine, main, &[]);
+        let flushed = io.output.flush();
+        let value = result?;
+        if let Err(e) = flushed {
+            return Err(RuntimeError { kind: RuntimeErrorKind::OutputFailed(e.to_string()), backtrace: vec![] });
+        }
+        return Ok(value);
+    }
+}
 
-fn lex_ir(mut code: &str) -> Vec<IRTok> {
-    let mut tokens: Vec<IRTok> = vec![];
-    while code.len() > 0 {
-        let (tok, rest) = lex_ir_token(code);
-        match tok {
+use crate::codegen::SourceMap;
+use crate::host::HostRegistry;
+use crate::vm_io::{InputSource, Reporter, VmIo};
+
+fn lex_ir(code: &str) -> Result<Vec<IRToken>, IRError> {
+    let mut tokens: Vec<IRToken> = vec![];
+    let mut rest = code;
+    let mut line: usize = 1;
+    let mut line_start: usize = 0;
+    while !rest.is_empty() {
+        // lex_ir_token skips leading whitespace itself, find where the token really starts.
+        let trimmed = rest.trim_start_matches(|c: char| c.is_whitespace() && c != '\n');
+        let start = code.len() - trimmed.len();
+        let column = code[line_start..start].chars().count() + 1;
+
+        let (tok, next) = lex_ir_token(rest);
+        let token = match tok {
         None => break,
-        Some(value) => tokens.push(value),
+        Some(value) => IRToken { tok: value, line, column },
+        };
+
+        match &token.tok {
+        IRTok::InvalidNumber(s) => {
+            return error_at(&token, format!("number literal '{s}' does not fit in a 32-bit integer ({} to {}).", i32::MIN, i32::MAX));
+        }
+        IRTok::InvalidChar(c) => {
+            return error_at(&token, format!("invalid character '{}' (U+{:04X}). identifiers may only use letters, digits and ASCII symbols.", c.escape_default(), *c as u32));
+        }
+        IRTok::Label(label) if label == ":" => {
+            return error_at(&token, String::from("missing label name after ':'."));
+        }
+        _ => tokens.push(token),
+        }
+
+        let consumed_start = code.len() - rest.len();
+        let consumed_end = code.len() - next.len();
+        for (i, c) in code[consumed_start..consumed_end].char_indices() {
+            if c == '\n' {
+                line += 1;
+                line_start = consumed_start + i + 1;
+            }
         }
-        code = rest;
+        rest = next;
     }
 
-    return tokens;
+    return Ok(tokens);
 }
 
 const MAX_LINE: usize = 2000000;
 
-fn parse_ir(tokens: &Vec<IRTok>, idx: &mut usize) -> Result< Vec<FunctionBytecode>, IRError> {
+fn parse_ir(tokens: &[IRToken], idx: &mut usize, host: &HostRegistry) -> Result< Vec<FunctionBytecode>, IRError> {
     let mut serialized_line: usize = 1;
     let mut vector: Vec<FunctionBytecode> = vec![];
     let mut has_main: bool = false;
@@ -60,25 +195,32 @@ fn parse_ir(tokens: &Vec<IRTok>, idx: &mut usize) -> Result< Vec<FunctionBytecod
         vector.push(bytecode);
     }
 
-    if has_main == false {
-      return error(MAX_LINE, format!("Error. Generated code does not have a main."));
+    if !has_main {
+      return error(MAX_LINE, String::from("Error. Generated code does not have a main."));
     }
 
-    // todo: this is not the correct line numbers. but I dunno how to get better line numbers...
+    // calls hold the index of the function name token until every function is known.
     for func_id in 0..vector.len() {
         for instr_id in 0..vector[func_id].body.len() {
             if let Bytecode::Call(r, call, params) = &vector[func_id].body[instr_id] {
-                if let IRTok::Var(func_name) = &tokens[*call] {
+                let call_token = &tokens[*call];
+                if let IRTok::Var(func_name) = &call_token.tok {
                     if let Some(call_value) = find_func_id(&vector, func_name) {
                          if params.len() != vector[call_value].parameters {
-                             return error(MAX_LINE, format!("Error. Invalid parameter passing to '{func_name}'. Expected {} number of parameters. Got {} number of parameters.", vector[call_value].parameters, params.len()));
+                             return error_at(call_token, format!("Invalid parameter passing to '{func_name}'. Expected {} number of parameters. Got {} number of parameters.", vector[call_value].parameters, params.len()));
                          }
                          vector[func_id].body[instr_id] = Bytecode::Call(*r, call_value, params.to_vec());
+                    } else if let Some(host_id) = host.find(func_name) {
+                         let arity = host.get(host_id).arity;
+                         if params.len() != arity {
+                             return error_at(call_token, format!("Invalid parameter passing to '{func_name}'. Expected {} number of parameters. Got {} number of parameters.", arity, params.len()));
+                         }
+                         vector[func_id].body[instr_id] = Bytecode::CallHost(*r, host_id, params.to_vec());
                     } else {
-                         return error(MAX_LINE, format!("Error. Undeclared function '{}'", func_name));
+                         return error_at(call_token, format!("Undeclared function '{}'", func_name));
                     }
                 } else {
-                    return error(MAX_LINE, format!("Internal Interpreter Error."));
+                    return error(MAX_LINE, String::from("Internal Interpreter Error."));
                 }
             }
         }
@@ -86,7 +228,7 @@ fn parse_ir(tokens: &Vec<IRTok>, idx: &mut usize) -> Result< Vec<FunctionBytecod
 
     return Ok(vector);
 
-    fn find_func_id(v: &Vec<FunctionBytecode>, func_name: &str) -> Option<usize> {
+    fn find_func_id(v: &[FunctionBytecode], func_name: &str) -> Option<usize> {
         for (i, function) in v.iter().enumerate() {
             if function.name.eq(func_name) {
                 return Some(i);
@@ -96,34 +238,150 @@ fn parse_ir(tokens: &Vec<IRTok>, idx: &mut usize) -> Result< Vec<FunctionBytecod
     }
 }
 
-struct IRError {
+#[derive(Debug)]
+pub struct IRError {
     line: usize,
+    // 0 when only the line is known.
+    column: usize,
     message: String,
 }
 
+impl IRError {
+    // None when the error is about the file as a whole.
+    pub fn line(&self) -> Option<usize> {
+        if self.line == MAX_LINE { None } else { Some(self.line) }
+    }
+
+    pub fn column(&self) -> Option<usize> {
+        if self.line == MAX_LINE || self.column == 0 { None } else { Some(self.column) }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl std::error::Error for IRError {}
+
 impl fmt::Display for IRError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if self.line == MAX_LINE {
             write!(f, "Error. {}", self.message)
-        } else {
+        } else if self.column == 0 {
             write!(f, "Error at line {}. {}", self.line, self.message)
+        } else {
+            write!(f, "Error at line {}, column {}. {}", self.line, self.column, self.message)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RuntimeErrorKind {
+    MissingMain,
+    HostMismatch { function: String, arity: usize },
+    HostFunction { function: String, message: String },
+    DivideByZero,
+    Overflow { symbol: &'static str, lhs: i32, rhs: i32 },
+    OutOfBounds { index: i32, len: usize },
+    InvalidBranch(i32),
+    EndOfInput,
+    InputFailed(String),
+    OutputFailed(String),
+    StackOverflow { function: String, max_depth: usize },
+    WrongArgumentCount { function: String, expected: usize, got: usize },
+    InstructionLimit(u64),
+    TimeLimit(Duration),
+    MemoryLimit { function: String, needed: usize, limit: usize },
+    Stopped,
+}
+
+impl fmt::Display for RuntimeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+        RuntimeErrorKind::MissingMain => write!(f, "No main function declared."),
+        RuntimeErrorKind::HostMismatch { function, arity } => write!(f, "The program calls host function '{function}' with {arity} parameters, which the runtime does not provide."),
+        RuntimeErrorKind::HostFunction { function, message } => write!(f, "Host function '{function}' failed. {message}"),
+        RuntimeErrorKind::DivideByZero => write!(f, "Attempt to divide by zero."),
+        RuntimeErrorKind::Overflow { symbol, lhs, rhs } => write!(f, "Integer overflow in '{lhs} {symbol} {rhs}'. The result does not fit in a 32-bit integer."),
+        RuntimeErrorKind::OutOfBounds { index, len } => write!(f, "Array out of bounds. Index {index}. Array Length {len}."),
+        RuntimeErrorKind::EndOfInput => write!(f, "%input reached the end of the input before a number was read."),
+        RuntimeErrorKind::InputFailed(e) => write!(f, "Failed to read the input. {e}"),
+        RuntimeErrorKind::OutputFailed(e) => write!(f, "Failed to write the output. {e}"),
+        RuntimeErrorKind::InvalidBranch(value) => write!(f, "Branch on a variable that is neither 0 or 1. The value is: {value}"),
+        RuntimeErrorKind::StackOverflow { function, max_depth } => write!(f, "Stack overflow in function '{function}' (maximum call depth {max_depth})."),
+        RuntimeErrorKind::WrongArgumentCount { function, expected, got } => write!(f, "Incorrect number of parameters passed to '{function}'. Expected {expected}, got {got} parameters"),
+        RuntimeErrorKind::InstructionLimit(limit) => write!(f, "Instruction limit reached. The program did not finish within {limit} instructions."),
+        RuntimeErrorKind::TimeLimit(limit) => write!(f, "Time limit reached. The program did not finish within {} ms.", limit.as_millis()),
+        RuntimeErrorKind::Stopped => write!(f, "The program was stopped before it finished."),
+        RuntimeErrorKind::MemoryLimit { function, needed, limit } => write!(f, "Array memory limit reached when calling '{function}'. The arrays would need {needed} bytes, the limit is {limit} bytes."),
+        }
+    }
+}
+
+// one active function when the error happened, innermost first.
+#[derive(Debug, PartialEq)]
+pub struct StackEntry {
+    pub function: String,
+    pub ir_line: usize,
+    pub source_line: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct RuntimeError {
+    pub kind: RuntimeErrorKind,
+    pub backtrace: Vec<StackEntry>,
+}
+
+impl std::error::Error for RuntimeError {}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Runtime Error. {}", self.kind)?;
+
+        // deep recursion repeats the same entry many times, print each run once.
+        let mut i = 0;
+        while i < self.backtrace.len() {
+            let entry = &self.backtrace[i];
+            let mut run = 1;
+            while i + run < self.backtrace.len() && self.backtrace[i + run] == *entry {
+                run += 1;
+            }
+            let at = if i == 0 { "at" } else { "called from" };
+            write!(f, "\n    {at} {}, IR line {}", entry.function, entry.ir_line)?;
+            if let Some(line) = entry.source_line {
+                write!(f, ", .tt line {line}")?;
+            }
+            if run > 1 {
+                write!(f, " [x{run}]")?;
+            }
+            i += run;
         }
+        Ok(())
     }
 }
 
 fn error<T>(line: usize, message: String) -> Result<T, IRError> {
-    Err(IRError {line: line, message: message})
+    Err(IRError {line, column: 0, message})
+}
+
+fn error_at<T>(token: &IRToken, message: String) -> Result<T, IRError> {
+    Err(IRError {line: token.line, column: token.column, message})
 }
 
 use std::fmt;
 
-fn parse_func_ir(serialized_line: &mut usize, tokens: &Vec<IRTok>, idx: &mut usize) -> Result<Option<FunctionBytecode>, IRError> {
+fn parse_func_ir(serialized_line: &mut usize, tokens: &[IRToken], idx: &mut usize) -> Result<Option<FunctionBytecode>, IRError> {
     let mut function_bytecode = FunctionBytecode {
         name:String::from(""),
         parameters:0,
-        id:0,
+        int_slots: 0,
+        array_lengths: vec![],
         variables: HashMap::new(),
         body: vec![],
+        lines: vec![],
+        labels: HashMap::new(),
+        int_names: vec![],
+        array_names: vec![],
     };
 
     loop {
@@ -139,7 +397,7 @@ fn parse_func_ir(serialized_line: &mut usize, tokens: &Vec<IRTok>, idx: &mut usi
     match next(tokens, idx) {
     Some(token) => {
         if !matches!(token, IRTok::Func) {
-            return error(*serialized_line, format!("func IR must begin with '%func'"));
+            return error(*serialized_line, String::from("func IR must begin with '%func'"));
         }
     }
  
@@ -156,8 +414,7 @@ fn parse_func_ir(serialized_line: &mut usize, tokens: &Vec<IRTok>, idx: &mut usi
 
     };
 
-    match peek_result(*serialized_line, tokens, *idx)? {
-    IRTok::LParen => {
+    if let IRTok::LParen = peek_result(*serialized_line, tokens, *idx)? {
         *idx += 1;
         loop {
             match next_result(*serialized_line, tokens, idx)? {
@@ -165,12 +422,12 @@ fn parse_func_ir(serialized_line: &mut usize, tokens: &Vec<IRTok>, idx: &mut usi
             IRTok::Int => {
                 match next_result(*serialized_line, tokens, idx)? {
                 IRTok::Var(param) => {
-                    if let Some(_) = function_bytecode.variables.get(param) {
+                    if function_bytecode.variables.contains_key(param) {
                         let f = format!("identifier {param} already defined");
                         return error(*serialized_line, f);
                     } else {
                         function_bytecode.parameters += 1;
-                        let id = get_id(&mut function_bytecode);
+                        let id = int_slot(&mut function_bytecode);
                         function_bytecode.variables.insert(param.clone(), VariableType::IntVar(id));
                     }
                     if matches!(peek_result(*serialized_line, tokens, *idx)?, IRTok::Comma) {
@@ -189,9 +446,6 @@ fn parse_func_ir(serialized_line: &mut usize, tokens: &Vec<IRTok>, idx: &mut usi
             }
         }
     }
-    _ => {}
-
-    }
 
     if !matches!(next_result(*serialized_line, tokens, idx)?, IRTok::EndInstr) {
         return error(*serialized_line, String::from("%func header must end with a newline"));
@@ -202,11 +456,22 @@ fn parse_func_ir(serialized_line: &mut usize, tokens: &Vec<IRTok>, idx: &mut usi
     let mut labels_hash: HashMap<String, usize> = Default::default();
     let mut line: usize = 0;
     loop {
+        let start = *idx;
         let bytecode = parse_instruction(serialized_line, line, &mut function_bytecode, &mut labels_hash, tokens, idx)?;
+
+        // remember which IR line the instruction came from, skipping the blank lines before it.
+        let mut first = start;
+        while first < *idx && matches!(tokens[first].tok, IRTok::EndInstr) {
+            first += 1;
+        }
+        let source_line = tokens[first.min(tokens.len() - 1)].line;
+
         if matches!(bytecode, Bytecode::End) {
+            function_bytecode.lines.push(source_line);
             break;
         }
         function_bytecode.body.push(bytecode); 
+        function_bytecode.lines.push(source_line);
         line += 1;
     }
 
@@ -218,33 +483,33 @@ fn parse_func_ir(serialized_line: &mut usize, tokens: &Vec<IRTok>, idx: &mut usi
     for i in 0..function_bytecode.body.len() {
         match &function_bytecode.body[i] {
         Bytecode::Jmp(index) => {
-            if let IRTok::Label(label_name) = &tokens[*index] {
+            if let IRTok::Label(label_name) = &tokens[*index].tok {
                 if let Some(id) = labels_hash.get(label_name) {
                     function_bytecode.body[i] = Bytecode::Jmp(*id);
                 } else {
-                    return error(i, format!("Error. invalid label {}", label_name));
+                    return error_at(&tokens[*index], format!("invalid label {}", label_name));
                 }
             } else {
                 return error(*serialized_line, String::from("Internal Compiler Error."));
             }
         }
         Bytecode::BranchIf(tf, index) => {
-            if let IRTok::Label(label_name) = &tokens[*index] {
+            if let IRTok::Label(label_name) = &tokens[*index].tok {
                 if let Some(id) = labels_hash.get(label_name) {
                     function_bytecode.body[i] = Bytecode::BranchIf(tf.clone(), *id);
                 } else {
-                    return error(*serialized_line, format!("Error. invalid label {}", label_name));
+                    return error_at(&tokens[*index], format!("invalid label {}", label_name));
                 }
             } else {
                 return error(*serialized_line, String::from("Internal Compiler Error."));
             }
         }
         Bytecode::BranchIfn(tf, index) => {
-            if let IRTok::Label(label_name) = &tokens[*index] {
+            if let IRTok::Label(label_name) = &tokens[*index].tok {
                 if let Some(id) = labels_hash.get(label_name) {
                     function_bytecode.body[i] = Bytecode::BranchIfn(tf.clone(), *id);
                 } else {
-                    return error(*serialized_line, format!("Error. invalid label {}", label_name));
+                    return error_at(&tokens[*index], format!("invalid label {}", label_name));
                 }
             } else {
                 return error(*serialized_line, String::from("Internal Compiler Error."));
@@ -253,270 +518,716 @@ fn parse_func_ir(serialized_line: &mut usize, tokens: &Vec<IRTok>, idx: &mut usi
         _ => {}
         }
     }
+    function_bytecode.labels = labels_hash;
+    name_slots(&mut function_bytecode);
     return Ok(Some(function_bytecode));
 }
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+// integers and arrays live in two separate slot spaces, both numbered densely from 0,
+// so a frame is two vectors indexed directly by the slots stored in the bytecode.
+// parameters always take the first integer slots.
+pub struct FunctionBytecode {
+    pub(crate) name: String,
+    pub(crate) parameters: usize,
+    pub(crate) int_slots: usize,
+    pub(crate) array_lengths: Vec<usize>,
+    pub(crate) variables: HashMap<String, VariableType>,
+    pub(crate) body: Vec<Bytecode>,
+
+    // IR source line of every entry in body.
+    pub(crate) lines: Vec<usize>,
+
+    // instruction index of every label, the names keep their ':'.
+    pub(crate) labels: HashMap<String, usize>,
+
+    // the reverse of 'variables', the IR name of every slot.
+    pub(crate) int_names: Vec<String>,
+    pub(crate) array_names: Vec<String>,
+}
+
+impl FunctionBytecode {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn parameters(&self) -> usize {
+        self.parameters
+    }
+
+    // number of IR instructions, not counting the final %endfunc.
+    pub fn len(&self) -> usize {
+        self.body.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // IR source line of the instruction at 'index', index len() is the %endfunc.
+    pub fn ir_line(&self, index: usize) -> usize {
+        self.lines[index]
+    }
+
+    pub fn label(&self, name: &str) -> Option<usize> {
+        self.labels.get(name).copied()
+    }
+
+    pub fn is_branch(&self, index: usize) -> bool {
+        matches!(self.body[index], Bytecode::BranchIf(_, _) | Bytecode::BranchIfn(_, _))
+    }
+
+    // the name of the label at 'index', if the instruction there is one.
+    pub fn label_name(&self, index: usize) -> Option<&str> {
+        self.labels.iter().find(|(_, i)| **i == index).map(|(name, _)| name.as_str())
+    }
+
+    // memory the arrays of one frame take, saturating instead of overflowing on 32-bit targets.
+    fn array_bytes(&self) -> usize {
+        self.array_lengths.iter().fold(0usize, |total, len| total.saturating_add(len.saturating_mul(size_of::<i32>())))
+    }
+}
 
-struct FunctionBytecode {
-    name: String,
-    parameters: usize,
-    id: i32,
-    variables: HashMap<String, VariableType>,
-    body: Vec<Bytecode>,
+// fills in the reverse of 'variables'.
+pub(crate) fn name_slots(function: &mut FunctionBytecode) {
+    function.int_names = vec![String::new(); function.int_slots];
+    function.array_names = vec![String::new(); function.array_lengths.len()];
+    for (name, variable) in &function.variables {
+        match variable {
+        VariableType::IntVar(id) => function.int_names[*id] = name.clone(),
+        VariableType::ArrayVar(id) => function.array_names[*id] = name.clone(),
+        }
+    }
 }
 
-fn get_id(function: &mut FunctionBytecode) -> i32 {
-    let id = function.id;
-    function.id += 1;
+fn int_slot(function: &mut FunctionBytecode) -> usize {
+    let id = function.int_slots;
+    function.int_slots += 1;
     id
 }
 
-fn read_integer_value(variables: &HashMap<i32, i32>, op: &Op) -> i32 {
+fn array_slot(function: &mut FunctionBytecode, len: i32) -> usize {
+    function.array_lengths.push(len as usize);
+    function.array_lengths.len() - 1
+}
+
+struct Frame {
+    ints: Vec<i32>,
+    arrays: Vec<Vec<i32>>,
+}
+
+impl Frame {
+    fn new(function: &FunctionBytecode) -> Frame {
+        let arrays = function.array_lengths.iter().map(|len| vec![0i32; *len]).collect();
+        Frame { ints: vec![0; function.int_slots], arrays }
+    }
+}
+
+fn read_integer_value(frame: &Frame, op: &Op) -> i32 {
     match op {
     Op::Num(num) => *num,
-    Op::Var(id) => *variables.get(&id).unwrap(),
+    Op::Var(id) => frame.ints[*id],
     }
 }
 
-fn run_program(stdin: &io::Stdin, calls: &Vec<FunctionBytecode>) {
-    let entry_point = {
-        let mut result = None;
-        for func in calls {
-            if func.name.eq("main") {
-                result = Some(func);
-            } 
+// what %add, %sub, %mult and %div do when the result does not fit in an i32.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverflowMode {
+    // stop with a runtime error.
+    Trap,
+    // two's complement wrap around, like release builds of rust.
+    Wrap,
+    // clamp to i32::MIN or i32::MAX.
+    Saturate,
+}
+
+pub struct VmConfig {
+    // number of frames that may be live at once, including main's.
+    pub max_call_depth: usize,
+    pub overflow: OverflowMode,
+
+    // value %input reads once the input has ended, None makes it a runtime error.
+    pub eof_value: Option<i32>,
+
+    // limits for programs that may never finish. an instruction is one executed IR line,
+    // the time limit is only checked every TIME_CHECK_INTERVAL instructions and can not
+    // interrupt a %input waiting for the user or a slow host function. time spent waiting
+    // in %input does not count towards it.
+    pub max_instructions: Option<u64>,
+    pub time_limit: Option<Duration>,
+
+    // bytes of all arrays in all live frames together.
+    pub max_array_memory: usize,
+}
+
+impl Default for VmConfig {
+    fn default() -> VmConfig {
+        VmConfig {
+            max_call_depth: 100_000,
+            overflow: OverflowMode::Trap,
+            eof_value: None,
+            max_instructions: None,
+            time_limit: None,
+            max_array_memory: 256 * 1024 * 1024,
         }
-        match result {
-        Some(r) => r,
-        None => {
-            println!("Runtime Error. No main function declared.");
-            return;
+    }
+}
+
+const TIME_CHECK_INTERVAL: u64 = 1024;
+
+// reads the next whitespace separated number for %input. invalid numbers are
+// reported and skipped, so an interactive user can simply type again.
+fn read_input(input: &mut dyn InputSource, reporter: &mut dyn Reporter, pending: &mut VecDeque<String>, eof_value: Option<i32>) -> Result<i32, RuntimeErrorKind> {
+    loop {
+        while let Some(token) = pending.pop_front() {
+            match token.parse::<i32>() {
+            Ok(num) => return Ok(num),
+            Err(_) => reporter.report(&format!("User Input Error. '{}' is not a valid number.", token)),
+            }
         }
+
+        let mut buf = String::with_capacity(64);
+        match input.read_line(&mut buf) {
+        Ok(0) => {
+            return match eof_value {
+            Some(num) => Ok(num),
+            None => Err(RuntimeErrorKind::EndOfInput),
+            };
+        }
+        Ok(_) => pending.extend(buf.split_whitespace().map(String::from)),
+        Err(e) => return Err(RuntimeErrorKind::InputFailed(e.to_string())),
         }
-    };
-    match run_bytecode(stdin, entry_point, calls, &vec![]) {
-    Ok(n) => println!("Run successful. Exit code {}", n),
-    Err(e) => println!("{}", e),
     }
 }
 
-fn run_bytecode(stdin: &io::Stdin, function: &FunctionBytecode, calls: &Vec<FunctionBytecode>, parameters: &Vec<i32>) -> Result<i32, IRError>  {
-    let mut variables: HashMap<i32, i32> = HashMap::new();
-    let mut arrays: HashMap<i32, Vec<i32>> = HashMap::new();
-
-    // setup local variables
-    for (_, vartype) in &function.variables {
-         match vartype {
-         VariableType::IntVar(id) => {
-             variables.insert(*id, 0);
-         }
+struct Arithmetic {
+    symbol: &'static str,
+    checked: fn(i32, i32) -> Option<i32>,
+    wrapping: fn(i32, i32) -> i32,
+    saturating: fn(i32, i32) -> i32,
+}
 
-         VariableType::ArrayVar(id, len) => {
-             // create an array of zeroes.
-             let arr = vec![0i32; *len as usize];
-             arrays.insert(*id, arr);
-         }
+const ADD: Arithmetic = Arithmetic { symbol: "+", checked: i32::checked_add, wrapping: i32::wrapping_add, saturating: i32::saturating_add };
+const SUB: Arithmetic = Arithmetic { symbol: "-", checked: i32::checked_sub, wrapping: i32::wrapping_sub, saturating: i32::saturating_sub };
+const MULT: Arithmetic = Arithmetic { symbol: "*", checked: i32::checked_mul, wrapping: i32::wrapping_mul, saturating: i32::saturating_mul };
+// only i32::MIN / -1 overflows, division by zero is checked before.
+const DIV: Arithmetic = Arithmetic { symbol: "/", checked: i32::checked_div, wrapping: i32::wrapping_div, saturating: i32::saturating_div };
 
-         }
+fn arithmetic(op: &Arithmetic, mode: OverflowMode, num1: i32, num2: i32) -> Result<i32, RuntimeErrorKind> {
+    match mode {
+    OverflowMode::Trap => (op.checked)(num1, num2).ok_or(RuntimeErrorKind::Overflow { symbol: op.symbol, lhs: num1, rhs: num2 }),
+    OverflowMode::Wrap => Ok((op.wrapping)(num1, num2)),
+    OverflowMode::Saturate => Ok((op.saturating)(num1, num2)),
     }
+}
+
+// a suspended caller, resumed at 'ip' once the callee returns into 'dest'.
+struct SavedFrame<'a> {
+    function: &'a FunctionBytecode,
+    frame: Frame,
+    ip: usize,
+    dest: usize,
+}
+
+// 'array_memory' is what the live frames already use, the new frame's arrays are added to it.
+fn enter_function(function: &FunctionBytecode, parameters: &[i32], array_memory: &mut usize, config: &VmConfig) -> Result<Frame, RuntimeErrorKind> {
     if parameters.len() != function.parameters {
-         let e = format!("Runtime Error. Incorrect number of parameters passed to the function. Expected {}, got {} parameters", function.parameters, parameters.len());
-         return error(MAX_LINE, e);
+         let (function, expected, got) = (function.name.clone(), function.parameters, parameters.len());
+         return Err(RuntimeErrorKind::WrongArgumentCount { function, expected, got });
+    }
+
+    // check before allocating, a huge %int[] would otherwise abort the whole process.
+    let needed = array_memory.saturating_add(function.array_bytes());
+    if needed > config.max_array_memory {
+         return Err(RuntimeErrorKind::MemoryLimit { function: function.name.clone(), needed, limit: config.max_array_memory });
+    }
+    *array_memory = needed;
+
+    // setup local variables, parameters are the first integer slots.
+    let mut frame = Frame::new(function);
+    frame.ints[..parameters.len()].copy_from_slice(parameters);
+    return Ok(frame);
+}
+
+// builds the backtrace from the current instruction outwards through every suspended caller.
+fn backtrace(function: &FunctionBytecode, instr_pointer: usize, stack: &[SavedFrame], source_map: Option<&SourceMap>) -> Vec<StackEntry> {
+    let mut entries: Vec<(&FunctionBytecode, usize)> = vec![(function, instr_pointer)];
+    for saved in stack.iter().rev() {
+        // the caller is suspended just past its '%call'.
+        entries.push((saved.function, saved.ip - 1));
+    }
+
+    return entries.iter().map(|(function, ip)| {
+        let ir_line = function.lines[*ip];
+        let source_line = source_map.and_then(|map| map.source_line(ir_line));
+        StackEntry { function: function.name.clone(), ir_line, source_line }
+    }).collect();
+}
+
+// lets tools such as the debugger follow a run. the hook is called before every
+// instruction, with the VM paused and its state readable through VmState.
+pub trait VmHook {
+    fn before_instruction(&mut self, state: &VmState) -> HookAction;
+
+    // called once the instruction ran without error, 'result' is the value it wrote, or for
+    // %ret and %endfunc the value returned. after a %call, 'state' is already in the callee.
+    fn after_instruction(&mut self, _state: &VmState, _result: Option<i32>) {}
+}
+
+pub enum HookAction {
+    Continue,
+    // end the run with RuntimeErrorKind::Stopped.
+    Stop,
+}
+
+// several hooks on one run, called in order. the run stops if any of them says so.
+pub struct Hooks<'a>(pub Vec<&'a mut dyn VmHook>);
+
+impl VmHook for Hooks<'_> {
+    fn before_instruction(&mut self, state: &VmState) -> HookAction {
+        let mut action = HookAction::Continue;
+        for hook in self.0.iter_mut() {
+            if let HookAction::Stop = hook.before_instruction(state) {
+                action = HookAction::Stop;
+            }
+        }
+        return action;
+    }
+
+    fn after_instruction(&mut self, state: &VmState, result: Option<i32>) {
+        for hook in self.0.iter_mut() {
+            hook.after_instruction(state, result);
+        }
     }
+}
+
+pub enum Local<'a> {
+    Int(i32),
+    Array(&'a [i32]),
+}
+
+pub struct VmState<'a> {
+    functions: &'a [FunctionBytecode],
+    host: &'a HostRegistry,
+    host_ids: &'a [usize],
+    function: &'a FunctionBytecode,
+    ip: usize,
+    frame: &'a Frame,
+    stack: &'a [SavedFrame<'a>],
+    source_map: Option<&'a SourceMap>,
+}
+
+impl<'a> VmState<'a> {
+    // every function of the program.
+    pub fn functions(&self) -> &'a [FunctionBytecode] {
+        self.functions
+    }
+
+    pub fn function(&self) -> &'a FunctionBytecode {
+        self.function
+    }
+
+    // index of the instruction about to execute.
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    pub fn ir_line(&self) -> usize {
+        self.function.lines[self.ip]
+    }
+
+    pub fn source_line(&self) -> Option<usize> {
+        self.source_map.and_then(|map| map.source_line(self.ir_line()))
+    }
+
+    // number of live frames, 1 while in main.
+    pub fn depth(&self) -> usize {
+        self.stack.len() + 1
+    }
+
+    // a variable of the current frame by its IR name.
+    pub fn local(&self, name: &str) -> Option<Local<'a>> {
+        match self.function.variables.get(name)? {
+        VariableType::IntVar(id) => Some(Local::Int(self.frame.ints[*id])),
+        VariableType::ArrayVar(id) => Some(Local::Array(&self.frame.arrays[*id])),
+        }
+    }
+
+    // every variable of the current frame in declaration order, integers first.
+    pub fn locals(&self) -> Vec<(&'a str, Local<'a>)> {
+        let mut names: Vec<(&VariableType, &'a str)> = self.function.variables.iter().map(|(name, id)| (id, name.as_str())).collect();
+        names.sort_by_key(|(id, _)| match id {
+        VariableType::IntVar(id) => (0, *id),
+        VariableType::ArrayVar(id) => (1, *id),
+        });
+        return names.iter().map(|(_, name)| (*name, self.local(name).unwrap())).collect();
+    }
+
+    pub fn backtrace(&self) -> Vec<StackEntry> {
+        backtrace(self.function, self.ip, self.stack, self.source_map)
+    }
+}
+
+// the value an instruction that stays in the same frame has just written.
+fn written_value(instr: &Bytecode, frame: &Frame) -> Option<i32> {
+    match instr {
+    Bytecode::In(dest) | Bytecode::Mov(MemWrite::IntVar(dest), _) | Bytecode::CallHost(dest, _, _) => Some(frame.ints[*dest]),
+    Bytecode::Add(dest, _, _) | Bytecode::Sub(dest, _, _) | Bytecode::Mult(dest, _, _) | Bytecode::Div(dest, _, _) | Bytecode::Mod(dest, _, _) => Some(frame.ints[*dest]),
+    Bytecode::LessThan(dest, _, _) | Bytecode::LessEqual(dest, _, _) | Bytecode::NotEqual(dest, _, _) => Some(frame.ints[*dest]),
+    Bytecode::Equal(dest, _, _) | Bytecode::GreaterEqual(dest, _, _) | Bytecode::GreaterThan(dest, _, _) => Some(frame.ints[*dest]),
+    Bytecode::Mov(MemWrite::ArrayWrite(dest, index), _) => {
+        let index = read_integer_value(frame, index);
+        frame.arrays[*dest].get(index as usize).copied()
+    }
+    _ => None,
+    }
+}
 
-    // hopefully this covers everything needed for parameter passing...
-    for (i, value) in parameters.iter().enumerate() {
-        let variable = variables.get_mut(&(i as i32)).unwrap();
-        *variable = *value;
+impl VmState<'_> {
+    // the opcode of the instruction about to execute, '%add', ':label' instructions give ':'.
+    pub fn opcode(&self) -> &'static str {
+        match &self.function.body[self.ip] {
+        Bytecode::End => "%endfunc",
+        Bytecode::Label => ":",
+        Bytecode::Int(_) => "%int",
+        Bytecode::IntArray(_) => "%int[]",
+        Bytecode::Out(_) => "%out",
+        Bytecode::In(_) => "%input",
+        Bytecode::Mov(_, _) => "%mov",
+        Bytecode::Add(_, _, _) => "%add",
+        Bytecode::Sub(_, _, _) => "%sub",
+        Bytecode::Mult(_, _, _) => "%mult",
+        Bytecode::Div(_, _, _) => "%div",
+        Bytecode::Mod(_, _, _) => "%mod",
+        Bytecode::LessThan(_, _, _) => "%lt",
+        Bytecode::LessEqual(_, _, _) => "%le",
+        Bytecode::NotEqual(_, _, _) => "%neq",
+        Bytecode::Equal(_, _, _) => "%eq",
+        Bytecode::GreaterEqual(_, _, _) => "%ge",
+        Bytecode::GreaterThan(_, _, _) => "%gt",
+        Bytecode::Call(_, _, _) | Bytecode::CallHost(_, _, _) => "%call",
+        Bytecode::Return(_) => "%ret",
+        Bytecode::Jmp(_) => "%jmp",
+        Bytecode::BranchIf(_, _) => "%branch_if",
+        Bytecode::BranchIfn(_, _) => "%branch_ifn",
+        }
+    }
+
+    // for a %branch_if or %branch_ifn about to execute, whether it will jump. None for other
+    // instructions and for a condition that is neither 0 or 1.
+    pub fn branch(&self) -> Option<bool> {
+        match &self.function.body[self.ip] {
+        Bytecode::BranchIf(value, _) => match read_integer_value(self.frame, value) {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        },
+        Bytecode::BranchIfn(value, _) => match read_integer_value(self.frame, value) {
+            0 => Some(true),
+            1 => Some(false),
+            _ => None,
+        },
+        _ => None,
+        }
+    }
+
+    // true when the instruction about to execute calls a function of the program, not a host function.
+    pub fn calls_function(&self) -> bool {
+        matches!(self.function.body[self.ip], Bytecode::Call(_, _, _))
+    }
+
+    // the instruction about to execute as IR text, with the current value of every
+    // variable it reads, e.g. '%add x, y=2, 3'. destinations only show their name.
+    pub fn disassemble(&self) -> String {
+        let function = self.function;
+        let int = |id: &usize| function.int_names[*id].as_str();
+        let array = |id: &usize| function.array_names[*id].as_str();
+        let op = |op: &Op| match op {
+        Op::Num(num) => num.to_string(),
+        Op::Var(id) => format!("{}={}", int(id), self.frame.ints[*id]),
+        };
+        let label = |target: &usize| function.label_name(*target).unwrap_or("?");
+        let ops3 = |opcode: &str, dest: &usize, src1: &Op, src2: &Op| format!("{opcode} {}, {}, {}", int(dest), op(src1), op(src2));
+        let call = |name: &str, dest: &usize, params: &[Op]| {
+            let params: Vec<String> = params.iter().map(op).collect();
+            format!("%call {}, {name}({})", int(dest), params.join(", "))
+        };
+
+        match &function.body[self.ip] {
+        Bytecode::End => String::from("%endfunc"),
+        Bytecode::Label => String::from(label(&self.ip)),
+        Bytecode::Int(id) => format!("%int {}", int(id)),
+        Bytecode::IntArray(id) => format!("%int[] {}, {}", array(id), function.array_lengths[*id]),
+        Bytecode::Out(value) => format!("%out {}", op(value)),
+        Bytecode::In(id) => format!("%input {}", int(id)),
+        Bytecode::Mov(dest, src) => {
+            let dest = match dest {
+            MemWrite::IntVar(id) => String::from(int(id)),
+            MemWrite::ArrayWrite(id, index) => format!("[{} + {}]", array(id), op(index)),
+            };
+            let src = match src {
+            MemRead::IntVar(id) => op(&Op::Var(*id)),
+            MemRead::Number(num) => num.to_string(),
+            MemRead::ArrayRead(id, index) => {
+                let element = read_memory(self.frame, src).map_or(String::new(), |value| format!("={value}"));
+                format!("[{} + {}]{element}", array(id), op(index))
+            }
+            };
+            format!("%mov {dest}, {src}")
+        }
+        Bytecode::Add(dest, src1, src2) => ops3("%add", dest, src1, src2),
+        Bytecode::Sub(dest, src1, src2) => ops3("%sub", dest, src1, src2),
+        Bytecode::Mult(dest, src1, src2) => ops3("%mult", dest, src1, src2),
+        Bytecode::Div(dest, src1, src2) => ops3("%div", dest, src1, src2),
+        Bytecode::Mod(dest, src1, src2) => ops3("%mod", dest, src1, src2),
+        Bytecode::LessThan(dest, src1, src2) => ops3("%lt", dest, src1, src2),
+        Bytecode::LessEqual(dest, src1, src2) => ops3("%le", dest, src1, src2),
+        Bytecode::NotEqual(dest, src1, src2) => ops3("%neq", dest, src1, src2),
+        Bytecode::Equal(dest, src1, src2) => ops3("%eq", dest, src1, src2),
+        Bytecode::GreaterEqual(dest, src1, src2) => ops3("%ge", dest, src1, src2),
+        Bytecode::GreaterThan(dest, src1, src2) => ops3("%gt", dest, src1, src2),
+        Bytecode::Call(dest, callee, params) => call(&self.functions[*callee].name, dest, params),
+        Bytecode::CallHost(dest, host_id, params) => call(&self.host.get(self.host_ids[*host_id]).name, dest, params),
+        Bytecode::Return(value) => format!("%ret {}", op(value)),
+        Bytecode::Jmp(target) => format!("%jmp {}", label(target)),
+        Bytecode::BranchIf(value, target) => format!("%branch_if {}, {}", op(value), label(target)),
+        Bytecode::BranchIfn(value, target) => format!("%branch_ifn {}, {}", op(value), label(target)),
+        }
     }
+}
+
+// what every frame of a run shares.
+struct Machine<'a, 'h> {
+    calls: &'a [FunctionBytecode],
+    config: &'a VmConfig,
+    source_map: Option<&'a SourceMap>,
+    host: &'a mut HostRegistry,
+
+    // the registry id of each host function in the program's signature.
+    host_ids: &'a [usize],
+    hook: Option<&'h mut dyn VmHook>,
+}
+
+// calls push the caller onto an explicit stack instead of recursing, so deep
+// recursion in the program is bounded by config.max_call_depth and not the rust stack.
+fn run_bytecode(io: &mut VmIo, machine: &mut Machine, function: &FunctionBytecode, parameters: &[i32]) -> Result<i32, RuntimeError>  {
+    let config = machine.config;
+    let calls = machine.calls;
+    let mut function = function;
+    let mut array_memory: usize = 0;
+    let mut frame = match enter_function(function, parameters, &mut array_memory, config) {
+    Ok(frame) => frame,
+    Err(kind) => return Err(RuntimeError { kind, backtrace: vec![] }),
+    };
+    let mut stack: Vec<SavedFrame> = vec![];
 
-    // a lot of unwraps, but we already checked everything
-    // so this should work.
-    // execute instructions.
+    // numbers read from the input but not consumed yet, a line may hold several.
+    let mut pending_input: VecDeque<String> = VecDeque::new();
+
+    // every slot was resolved by the parser, so plain indexing cannot fail.
+    // execute instructions. errors break out of the loop with the state
+    // still pointing at the failing instruction.
     let mut instr_pointer: usize = 0;
-    loop {
+    let mut executed: u64 = 0;
+    let start = Instant::now();
+    let mut waited = Duration::ZERO;
+    let kind = 'run: loop {
+        if config.max_instructions.is_some_and(|limit| executed >= limit) {
+            break 'run RuntimeErrorKind::InstructionLimit(executed);
+        }
+        executed += 1;
+        if executed.is_multiple_of(TIME_CHECK_INTERVAL) {
+            if let Some(limit) = config.time_limit {
+                if start.elapsed().saturating_sub(waited) > limit {
+                    break 'run RuntimeErrorKind::TimeLimit(limit);
+                }
+            }
+        }
+
+        if let Some(hook) = machine.hook.as_mut() {
+            let state = VmState { functions: calls, host: machine.host, host_ids: machine.host_ids, function, ip: instr_pointer, frame: &frame, stack: &stack, source_map: machine.source_map };
+            if let HookAction::Stop = hook.before_instruction(&state) {
+                break 'run RuntimeErrorKind::Stopped;
+            }
+        }
+
         let instr = &function.body[instr_pointer];
         match instr {
-        Bytecode::End => {
-            break;
+        Bytecode::End | Bytecode::Return(_) => {
+            let value = match instr {
+            Bytecode::Return(src1) => read_integer_value(&frame, src1),
+            _ => 0,
+            };
+            array_memory -= function.array_bytes();
+            match stack.pop() {
+            None => {
+                if let Some(hook) = machine.hook.as_mut() {
+                    let state = VmState { functions: calls, host: machine.host, host_ids: machine.host_ids, function, ip: instr_pointer, frame: &frame, stack: &stack, source_map: machine.source_map };
+                    hook.after_instruction(&state, Some(value));
+                }
+                return Ok(value);
+            }
+            Some(caller) => {
+                function = caller.function;
+                frame = caller.frame;
+                frame.ints[caller.dest] = value;
+                instr_pointer = caller.ip;
+            }
+            }
         }
 
         Bytecode::Int(id) => {
-            let value = variables.get_mut(id).unwrap();
-            *value = 0;
+            frame.ints[*id] = 0;
             instr_pointer += 1;
         }
 
-        Bytecode::IntArray(id, len) => {
-            let array = arrays.get_mut(id).unwrap();
-            for i in 0..*len {
-                 let idx = i as usize;
-                 array[idx] ^= array[idx];
-            }
+        Bytecode::IntArray(id) => {
+            frame.arrays[*id].fill(0);
             instr_pointer += 1;
         }
 
         Bytecode::Out(value) => {
-            let num = read_integer_value(&variables, value);
-            println!("{}", num);
+            let num = read_integer_value(&frame, value);
+            if let Err(e) = io.output.write_number(num) {
+                break 'run RuntimeErrorKind::OutputFailed(e.to_string());
+            }
             instr_pointer += 1;
         }
 
         Bytecode::In(id) => {
-            let mut buf = String::with_capacity(64);
-            loop {
-                match stdin.read_line(&mut buf) {
-                Ok(_) => {
-                     let token = buf.trim_end();
-                     match token.parse::<i32>() {
-                     Ok(num) => {
-                          let dest = variables.get_mut(id).unwrap();
-                          *dest = num;
-                          break;
-                     }
-
-                     Err(_) => {
-                         println!("User Input Error. '{}' is not a valid number.", token);
-                         buf.clear();
-                     }
-
-                     }
-                }
-
-                Err(e) => {
-                     println!("Error. Failed to read from standard input correctly.");
-                     println!("{e}");
-                     println!("Please try again.");
-                }
-
-                }
-            }
+            let before = Instant::now();
+            let result = read_input(io.input, io.reporter, &mut pending_input, config.eof_value);
+            waited += before.elapsed();
+            frame.ints[*id] = match result {
+            Ok(num) => num,
+            Err(kind) => break 'run kind,
+            };
             instr_pointer += 1;
         }
 
         Bytecode::Mov(MemWrite::IntVar(dest), src) => {
-            let num = read_memory(&variables, &arrays, src)?;
-            let dest = variables.get_mut(dest).unwrap();
-            *dest = num;
+            let num = match read_memory(&frame, src) {
+            Ok(num) => num,
+            Err(kind) => break 'run kind,
+            };
+            frame.ints[*dest] = num;
             instr_pointer += 1;
         }
 
         Bytecode::Mov(MemWrite::ArrayWrite(dest, index), src) => {
-            let num = read_memory(&variables, &arrays, src)?;
-            let dest = arrays.get_mut(dest).unwrap();
-            let index = read_integer_value(&variables, index);
-            if index < 0 {
-                let e = format!("Runtime Error: Array out of bounds. Value {}. Array Length {}", index, dest.len());
-                return error(MAX_LINE, e);
-            }
-            let i = index as usize;
-            if i < dest.len() {
-                dest[i] = num;
+            let num = match read_memory(&frame, src) {
+            Ok(num) => num,
+            Err(kind) => break 'run kind,
+            };
+            let index = read_integer_value(&frame, index);
+            let dest = &mut frame.arrays[*dest];
+            if index >= 0 && (index as usize) < dest.len() {
+                dest[index as usize] = num;
                 instr_pointer += 1;
             } else {
-                let e = format!("Runtime Error: Array out of bounds. Value {}. Array Length {}", i, dest.len());
-                return error(MAX_LINE, e);
+                break 'run RuntimeErrorKind::OutOfBounds { index, len: dest.len() };
             }
         }
 
         Bytecode::Add(dest, src1, src2) => {
-            let num1 = read_integer_value(&variables, src1);
-            let num2 = read_integer_value(&variables, src2);
-            let dest = variables.get_mut(dest).unwrap();
-            *dest = num1 + num2;
+            let num1 = read_integer_value(&frame, src1);
+            let num2 = read_integer_value(&frame, src2);
+            frame.ints[*dest] = match arithmetic(&ADD, config.overflow, num1, num2) {
+            Ok(num) => num,
+            Err(kind) => break 'run kind,
+            };
             instr_pointer += 1;
         }
 
         Bytecode::Sub(dest, src1, src2) => {
-            let num1 = read_integer_value(&variables, src1);
-            let num2 = read_integer_value(&variables, src2);
-            let dest = variables.get_mut(dest).unwrap();
-            *dest = num1 - num2;
+            let num1 = read_integer_value(&frame, src1);
+            let num2 = read_integer_value(&frame, src2);
+            frame.ints[*dest] = match arithmetic(&SUB, config.overflow, num1, num2) {
+            Ok(num) => num,
+            Err(kind) => break 'run kind,
+            };
             instr_pointer += 1;
         }
 
         Bytecode::Mult(dest, src1, src2) => {
-            let num1 = read_integer_value(&variables, src1);
-            let num2 = read_integer_value(&variables, src2);
-            let dest = variables.get_mut(dest).unwrap();
-            *dest = num1 * num2;
+            let num1 = read_integer_value(&frame, src1);
+            let num2 = read_integer_value(&frame, src2);
+            frame.ints[*dest] = match arithmetic(&MULT, config.overflow, num1, num2) {
+            Ok(num) => num,
+            Err(kind) => break 'run kind,
+            };
             instr_pointer += 1;
         }
 
         Bytecode::Div(dest, src1, src2) => {
-            let num1 = read_integer_value(&variables, src1);
-            let num2 = read_integer_value(&variables, src2);
+            let num1 = read_integer_value(&frame, src1);
+            let num2 = read_integer_value(&frame, src2);
             if num2 == 0 {
-                let e = String::from("Error. Attempt to divide by zero.");
-                return error(MAX_LINE, e);
+                break 'run RuntimeErrorKind::DivideByZero;
             }
-            let dest = variables.get_mut(dest).unwrap();
-            *dest = num1 / num2;
+            frame.ints[*dest] = match arithmetic(&DIV, config.overflow, num1, num2) {
+            Ok(num) => num,
+            Err(kind) => break 'run kind,
+            };
             instr_pointer += 1;
         }
 
         Bytecode::Mod(dest, src1, src2) => {
-            let num1 = read_integer_value(&variables, src1);
-            let num2 = read_integer_value(&variables, src2);
+            let num1 = read_integer_value(&frame, src1);
+            let num2 = read_integer_value(&frame, src2);
             if num2 == 0 {
-                let e = String::from("Error. Attempt to divide by zero.");
-                return error(MAX_LINE, e);
+                break 'run RuntimeErrorKind::DivideByZero;
             }
-            let dest = variables.get_mut(dest).unwrap();
-            *dest = num1 % num2;
+            // i32::MIN % -1 is 0 mathematically, only the machine instruction overflows.
+            frame.ints[*dest] = num1.wrapping_rem(num2);
             instr_pointer += 1;
         }
 
         Bytecode::LessThan(dest, src1, src2) => {
-            let num1 = read_integer_value(&variables, src1);
-            let num2 = read_integer_value(&variables, src2);
-            let dest = variables.get_mut(dest).unwrap();
-            *dest = (num1 < num2) as i32;
+            let num1 = read_integer_value(&frame, src1);
+            let num2 = read_integer_value(&frame, src2);
+            frame.ints[*dest] = (num1 < num2) as i32;
             instr_pointer += 1;
         }
 
         Bytecode::LessEqual(dest, src1, src2) => {
-            let num1 = read_integer_value(&variables, src1);
-            let num2 = read_integer_value(&variables, src2);
-            let dest = variables.get_mut(dest).unwrap();
-            *dest = (num1 <= num2) as i32;
+            let num1 = read_integer_value(&frame, src1);
+            let num2 = read_integer_value(&frame, src2);
+            frame.ints[*dest] = (num1 <= num2) as i32;
             instr_pointer += 1;
         }
 
         Bytecode::Equal(dest, src1, src2) => {
-            let num1 = read_integer_value(&variables, src1);
-            let num2 = read_integer_value(&variables, src2);
-            let dest = variables.get_mut(dest).unwrap();
-            *dest = (num1 == num2) as i32;
+            let num1 = read_integer_value(&frame, src1);
+            let num2 = read_integer_value(&frame, src2);
+            frame.ints[*dest] = (num1 == num2) as i32;
             instr_pointer += 1;
         }
 
         Bytecode::NotEqual(dest, src1, src2) => {
-            let num1 = read_integer_value(&variables, src1);
-            let num2 = read_integer_value(&variables, src2);
-            let dest = variables.get_mut(dest).unwrap();
-            *dest = (num1 != num2) as i32;
+            let num1 = read_integer_value(&frame, src1);
+            let num2 = read_integer_value(&frame, src2);
+            frame.ints[*dest] = (num1 != num2) as i32;
             instr_pointer += 1;
         }
 
         Bytecode::GreaterThan(dest, src1, src2) => {
-            let num1 = read_integer_value(&variables, src1);
-            let num2 = read_integer_value(&variables, src2);
-            let dest = variables.get_mut(dest).unwrap();
-            *dest = (num1 > num2) as i32;
+            let num1 = read_integer_value(&frame, src1);
+            let num2 = read_integer_value(&frame, src2);
+            frame.ints[*dest] = (num1 > num2) as i32;
             instr_pointer += 1;
         }
 
         Bytecode::GreaterEqual(dest, src1, src2) => {
-            let num1 = read_integer_value(&variables, src1);
-            let num2 = read_integer_value(&variables, src2);
-            let dest = variables.get_mut(dest).unwrap();
-            *dest = (num1 >= num2) as i32;
+            let num1 = read_integer_value(&frame, src1);
+            let num2 = read_integer_value(&frame, src2);
+            frame.ints[*dest] = (num1 >= num2) as i32;
             instr_pointer += 1;
         }
 
-        Bytecode::Label(_) => {
+        Bytecode::Label => {
             instr_pointer += 1;
         }
 
@@ -525,52 +1236,74 @@ fn run_bytecode(stdin: &io::Stdin, function: &FunctionBytecode, calls: &Vec<Func
         }
 
         Bytecode::BranchIf(src1, jump) => {
-            let num1 = read_integer_value(&variables, src1);
+            let num1 = read_integer_value(&frame, src1);
             match num1 {
             0 => {instr_pointer += 1;}
             1 => {instr_pointer = *jump;}
-            _ => {
-                let e = format!("Runtime Error. Branch on a variable that is neither 0 or 1. The value is: {}", num1);
-                return error(MAX_LINE, e);
-            }
+            _ => break 'run RuntimeErrorKind::InvalidBranch(num1),
             }
         }
 
         Bytecode::BranchIfn(src1, jump) => {
-            let num1 = read_integer_value(&variables, src1);
+            let num1 = read_integer_value(&frame, src1);
             match num1 {
             0 => {instr_pointer = *jump;}
             1 => {instr_pointer += 1;}
-            _ => {
-                let e = format!("Runtime Error. Branch on a variable that is neither 0 or 1. The value is: {}", num1);
-                return error(MAX_LINE, e);
-            }
+            _ => break 'run RuntimeErrorKind::InvalidBranch(num1),
             }
         }
 
         Bytecode::Call(dest, function_index, parameters) => {
-             let function = &calls[*function_index];
+             let callee = &calls[*function_index];
              let mut pass = vec![];
              for p in parameters {
-                  let num1: i32 = read_integer_value(&variables, p);
+                  let num1: i32 = read_integer_value(&frame, p);
                   pass.push(num1);
              }
 
-             let eax = run_bytecode(stdin, function, calls, &pass)?;
-             let dest = variables.get_mut(dest).unwrap();
-             *dest = eax;
+             // the caller's frame and this call would make stack.len() + 2 live frames.
+             if stack.len() + 2 > config.max_call_depth {
+                 break 'run RuntimeErrorKind::StackOverflow { function: callee.name.clone(), max_depth: config.max_call_depth };
+             }
+
+             let callee_frame = match enter_function(callee, &pass, &mut array_memory, config) {
+             Ok(frame) => frame,
+             Err(kind) => break 'run kind,
+             };
+             let caller_frame = std::mem::replace(&mut frame, callee_frame);
+             stack.push(SavedFrame { function, frame: caller_frame, ip: instr_pointer + 1, dest: *dest });
+             function = callee;
+             instr_pointer = 0;
+        }
+
+        Bytecode::CallHost(dest, host_id, parameters) => {
+             let pass: Vec<i32> = parameters.iter().map(|p| read_integer_value(&frame, p)).collect();
+             let host_id = machine.host_ids[*host_id];
+             match machine.host.call(host_id, &pass) {
+             Ok(value) => frame.ints[*dest] = value,
+             Err(message) => break 'run RuntimeErrorKind::HostFunction { function: machine.host.get(host_id).name.clone(), message },
+             }
              instr_pointer += 1;
         }
 
-        Bytecode::Return(src1) => {
-            let num = read_integer_value(&variables, src1);
-            return Ok(num);
         }
 
+        if let Some(hook) = machine.hook.as_mut() {
+            let result = match instr {
+            // the value returned is now in the caller, in the destination of its %call.
+            Bytecode::End | Bytecode::Return(_) => match &function.body[instr_pointer - 1] {
+                Bytecode::Call(dest, _, _) => Some(frame.ints[*dest]),
+                _ => None,
+            },
+            _ => written_value(instr, &frame),
+            };
+            let state = VmState { functions: calls, host: machine.host, host_ids: machine.host_ids, function, ip: instr_pointer, frame: &frame, stack: &stack, source_map: machine.source_map };
+            hook.after_instruction(&state, result);
         }
-    }
+    };
 
-    return Ok(0);
+    let backtrace = backtrace(function, instr_pointer, &stack, machine.source_map);
+    return Err(RuntimeError { kind, backtrace });
 }
 
 fn lookup_integer_variable_id(line: usize, function: &FunctionBytecode, ident: &String) -> Result<Op, IRError> {
@@ -578,7 +1311,7 @@ fn lookup_integer_variable_id(line: usize, function: &FunctionBytecode, ident: &
          match id {
          VariableType::IntVar(id) => Ok(Op::Var(*id)),
 
-         VariableType::ArrayVar(_,_) => {
+         VariableType::ArrayVar(_) => {
              let f = format!("invalid operand. '{}' is an array, not an integer.", ident);
              return error(line, f);
          }
@@ -590,12 +1323,12 @@ fn lookup_integer_variable_id(line: usize, function: &FunctionBytecode, ident: &
     }
 }
 
-fn lookup_variable_dest_id(line: usize, function: &FunctionBytecode, ident: &String) -> Result<i32, IRError> {
+fn lookup_variable_dest_id(line: usize, function: &FunctionBytecode, ident: &String) -> Result<usize, IRError> {
     if let Some(id) = function.variables.get(ident) {
          match id {
          VariableType::IntVar(id) => Ok(*id),
 
-         VariableType::ArrayVar(_,_) => {
+         VariableType::ArrayVar(_) => {
              let f = format!("invalid operand. '{}' is an array, not an integer.", ident);
              return error(line, f);
          }
@@ -607,7 +1340,7 @@ fn lookup_variable_dest_id(line: usize, function: &FunctionBytecode, ident: &Str
     }
 }
 
-fn parse_instruction(serialized_line: &mut usize, line: usize, function: &mut FunctionBytecode, labels_hash: &mut HashMap<String, usize>, tokens: &Vec<IRTok>, idx: &mut usize) -> Result<Bytecode, IRError> {
+fn parse_instruction(serialized_line: &mut usize, line: usize, function: &mut FunctionBytecode, labels_hash: &mut HashMap<String, usize>, tokens: &[IRToken], idx: &mut usize) -> Result<Bytecode, IRError> {
 
     // get rid of newlines.
     loop {
@@ -631,11 +1364,11 @@ fn parse_instruction(serialized_line: &mut usize, line: usize, function: &mut Fu
         _ => return error(*serialized_line, String::from("invalid instruction. expected identifier like '%int variable'")),
         };
 
-        if let Some(_) = function.variables.get(ident) {
+        if function.variables.contains_key(ident) {
              let f = format!("invalid instruction. identifier '{}' declared too many times", ident);
              return error(*serialized_line, f);
         } else {
-             let id = get_id(function);
+             let id = int_slot(function);
              function.variables.insert(ident.clone(), VariableType::IntVar(id));
              bytecode = Bytecode::Int(id);
         }
@@ -664,13 +1397,13 @@ fn parse_instruction(serialized_line: &mut usize, line: usize, function: &mut Fu
           return error(*serialized_line, String::from("array size cannot be less than or equal to zero."));
         }
 
-        if let Some(_) = function.variables.get(ident) {
+        if function.variables.contains_key(ident) {
              let f = format!("invalid instruction. identifier '{}' declared too many times", ident);
              return error(*serialized_line, f);
         } else {
-             let id = get_id(function);
-             function.variables.insert(ident.clone(), VariableType::ArrayVar(id, num));
-             bytecode = Bytecode::IntArray(id, num);
+             let id = array_slot(function, num);
+             function.variables.insert(ident.clone(), VariableType::ArrayVar(id));
+             bytecode = Bytecode::IntArray(id);
         }
     }
 
@@ -777,7 +1510,7 @@ fn parse_instruction(serialized_line: &mut usize, line: usize, function: &mut Fu
                      match id {
                      VariableType::IntVar(id) => MemRead::IntVar(*id),
          
-                     VariableType::ArrayVar(_,_) => {
+                     VariableType::ArrayVar(_) => {
                          let f = format!("invalid '%mov' statement. {} is an array, not an integer.", ident);
                          return error(*serialized_line, f);
                      }
@@ -794,7 +1527,7 @@ fn parse_instruction(serialized_line: &mut usize, line: usize, function: &mut Fu
                 (IRTok::Var(array), IRTok::Plus, IRTok::Num(index), IRTok::RBrace) => {
                     if let Some(id) = function.variables.get(array) {
                         match id {
-                        VariableType::ArrayVar(id,_) => {
+                        VariableType::ArrayVar(id) => {
                             MemRead::ArrayRead(*id, Op::Num(*index))
                         }
                         _ => {
@@ -812,7 +1545,7 @@ fn parse_instruction(serialized_line: &mut usize, line: usize, function: &mut Fu
                 (IRTok::Var(array), IRTok::Plus, IRTok::Var(variable), IRTok::RBrace) => {
                     if let Some(id) = function.variables.get(array) {
                         match id {
-                        VariableType::ArrayVar(id,_) => {
+                        VariableType::ArrayVar(id) => {
                             
                             MemRead::ArrayRead(*id, lookup_integer_variable_id(*serialized_line, function, variable)?)
                         }
@@ -851,7 +1584,7 @@ fn parse_instruction(serialized_line: &mut usize, line: usize, function: &mut Fu
                         return error(*serialized_line, f);
                     }
          
-                    VariableType::ArrayVar(dest,_) => {
+                    VariableType::ArrayVar(dest) => {
                         *dest
                     }
 
@@ -888,7 +1621,7 @@ fn parse_instruction(serialized_line: &mut usize, line: usize, function: &mut Fu
                      match id {
                      VariableType::IntVar(id) => MemRead::IntVar(*id),
          
-                     VariableType::ArrayVar(_,_) => {
+                     VariableType::ArrayVar(_) => {
                          let f = format!("invalid '%mov' statement. {} is an array, not an integer.", ident);
                          return error(*serialized_line, f);
                      }
@@ -995,12 +1728,12 @@ fn parse_instruction(serialized_line: &mut usize, line: usize, function: &mut Fu
 
     IRTok::Label(name) => {
         *idx += 1;
-        if let None = labels_hash.get(name) {
+        if !labels_hash.contains_key(name) {
             labels_hash.insert(name.clone(), line);
         } else {
             return error(*serialized_line, format!("label {} already defined.", name));
         }
-        bytecode = Bytecode::Label(line);
+        bytecode = Bytecode::Label;
         if !matches!(peek_result(*serialized_line, tokens, *idx)?, IRTok::EndInstr) {
             return error(*serialized_line, format!("invalid opcode '{}'. labels can be declared using ':label'", name));
         }
@@ -1072,7 +1805,7 @@ fn parse_instruction(serialized_line: &mut usize, line: usize, function: &mut Fu
     return Ok(bytecode);
 }
 
-fn addr_code3(serialized_line: usize, function: &FunctionBytecode, tokens: &Vec<IRTok>, idx: &mut usize) -> Result<(i32, Op, Op), IRError> {
+fn addr_code3(serialized_line: usize, function: &FunctionBytecode, tokens: &[IRToken], idx: &mut usize) -> Result<(usize, Op, Op), IRError> {
     let dest = match next_result(serialized_line, tokens, idx)? {
     IRTok::Var(ident) => lookup_variable_dest_id(serialized_line, function, ident)?,
     _ => return error(serialized_line, String::from("invalid dest.")),
@@ -1101,37 +1834,37 @@ fn addr_code3(serialized_line: usize, function: &FunctionBytecode, tokens: &Vec<
     return Ok((dest, src1, src2));
 }
 
-fn next<'a>(tokens: &'a Vec<IRTok>, index: &mut usize) -> Option<&'a IRTok> {
+fn next<'a>(tokens: &'a [IRToken], index: &mut usize) -> Option<&'a IRTok> {
     if *index < tokens.len() {
         let ret = *index;
         *index += 1;
-        return Some(&tokens[ret])
+        return Some(&tokens[ret].tok)
     } else {
         return None
     }
 }
 
-fn peek_result<'a>(serialized_line: usize, tokens: &'a Vec<IRTok>, index: usize) -> Result<&'a IRTok, IRError> {
+fn peek_result(serialized_line: usize, tokens: &[IRToken], index: usize) -> Result<&IRTok, IRError> {
     if index < tokens.len() {
-        return Ok(&tokens[index]);
+        return Ok(&tokens[index].tok);
     } else {
         return error(serialized_line, String::from("unexpected end."));
     }
 }
 
-fn peek<'a>(tokens: &'a Vec<IRTok>, index: usize) -> Option<&'a IRTok>{
+fn peek(tokens: &[IRToken], index: usize) -> Option<&IRTok>{
     if index < tokens.len() {
-        return Some(&tokens[index])
+        return Some(&tokens[index].tok)
     } else {
         return None
     }
 }
 
-fn next_result<'a>(serialized_line: usize, tokens: &'a Vec<IRTok>, index: &mut usize) -> Result<&'a IRTok, IRError> {
+fn next_result<'a>(serialized_line: usize, tokens: &'a [IRToken], index: &mut usize) -> Result<&'a IRTok, IRError> {
     if *index < tokens.len() {
         let ret = *index;
         *index += 1;
-        return Ok(&tokens[ret])
+        return Ok(&tokens[ret].tok)
     } else {
         return error(serialized_line, String::from("unexpected end."))
     }
@@ -1179,15 +1912,25 @@ fn lex_ir_token(mut code: &str) -> (Option<IRTok>, &str) {
         }
     }
 
+    // identifiers and labels accept any printable ASCII as before, and beyond ASCII only
+    // letters and digits ('größe', 'π'), so stray symbols or invisible characters copied
+    // into the IR are reported instead of becoming part of a name. comments may contain anything.
+    fn is_ident_char(c: char) -> bool {
+        if c.is_ascii() {
+            return !c.is_ascii_control();
+        }
+        return c.is_alphanumeric();
+    }
+
     fn num_literal(s: &str) -> Option<IRTok> {
         match s.parse::<i32>() {
         Ok(val) => Some(IRTok::Num(val)),
-        Err(_) => None,
+        Err(_) => Some(IRTok::InvalidNumber(String::from(s))),
         }
     }
 
-    // skip left whitespace.
-    for (i, c) in code.chars().enumerate() {
+    // skip left whitespace. 'i' is always a byte offset, so slicing stays on char boundaries.
+    for (i, c) in code.char_indices() {
         if c.is_whitespace() && c != '\n' {
             continue;
         }
@@ -1197,7 +1940,7 @@ fn lex_ir_token(mut code: &str) -> (Option<IRTok>, &str) {
  
     let mut state = StateMachine::Initial;
 
-    for (i, c) in code.chars().enumerate() {
+    for (i, c) in code.char_indices() {
         state = match state {
 
         StateMachine::Initial => {
@@ -1217,7 +1960,10 @@ fn lex_ir_token(mut code: &str) -> (Option<IRTok>, &str) {
             '+' => return (Some(IRTok::Plus), &code[i + 1..]),
             ']' => return (Some(IRTok::RBrace), &code[i + 1..]),
             '0'..='9' => StateMachine::Num,
+            // a '-' directly followed by a digit starts a negative number literal.
+            '-' if code[i + 1..].starts_with(|d: char| d.is_ascii_digit()) => StateMachine::Num,
             ';' => StateMachine::Comments,
+            _ if !is_ident_char(c) => return (Some(IRTok::InvalidChar(c)), &code[i + c.len_utf8()..]),
             _ => StateMachine::Ident,
             }
         }
@@ -1229,7 +1975,7 @@ fn lex_ir_token(mut code: &str) -> (Option<IRTok>, &str) {
             }
             if c.is_whitespace() {
                 let tok = opcode(&code[..i]);
-                return (tok, &code[i+1..]);
+                return (tok, &code[i + c.len_utf8()..]);
             }
 
             StateMachine::Lit
@@ -1242,7 +1988,11 @@ fn lex_ir_token(mut code: &str) -> (Option<IRTok>, &str) {
             }
             if c.is_whitespace() {
                 let tok = &code[..i];
-                return (Some(IRTok::Label(String::from(tok))), &code[i+1..]);
+                return (Some(IRTok::Label(String::from(tok))), &code[i + c.len_utf8()..]);
+            }
+
+            if !is_ident_char(c) {
+                return (Some(IRTok::InvalidChar(c)), &code[i + c.len_utf8()..]);
             }
 
             StateMachine::Label
@@ -1264,14 +2014,18 @@ fn lex_ir_token(mut code: &str) -> (Option<IRTok>, &str) {
 
             if c.is_whitespace() {
                 let tok = IRTok::Var(String::from(&code[..i]));
-                return (Some(tok), &code[i+1..]);
+                return (Some(tok), &code[i + c.len_utf8()..]);
+            }
+
+            if !is_ident_char(c) {
+                return (Some(IRTok::InvalidChar(c)), &code[i + c.len_utf8()..]);
             }
 
             StateMachine::Ident
         }
 
         StateMachine::Num => {
-            if c >= '0' && c <= '9' {
+            if c.is_ascii_digit() {
                 StateMachine::Num
             } else {
                 let tok = num_literal(&code[..i]);
@@ -1294,10 +2048,22 @@ fn lex_ir_token(mut code: &str) -> (Option<IRTok>, &str) {
         return (Some(tok), "");
     }
 
+    StateMachine::Label => {
+        return (Some(IRTok::Label(String::from(code))), "");
+    }
 
-    _ => {
-        println!("{:?} {}", state, code);
-        todo!()
+    StateMachine::Num => {
+        return (num_literal(code), "");
+    }
+
+    // a comment on the last line ends the instruction like a newline would.
+    StateMachine::Comments => {
+        return (Some(IRTok::EndInstr), "");
+    }
+
+    // only whitespace was left.
+    StateMachine::Initial => {
+        return (None, "");
     }
 
     }
@@ -1305,7 +2071,18 @@ fn lex_ir_token(mut code: &str) -> (Option<IRTok>, &str) {
 
 #[cfg(test)]
 mod ir_tests {
-    use crate::interpeter::*;
+    use crate::interpreter::*;
+    use crate::vm_io::*;
+
+    fn run_silent(function: &FunctionBytecode, calls: &[FunctionBytecode], parameters: &[i32], config: &VmConfig, source_map: Option<&SourceMap>) -> Result<i32, RuntimeError> {
+        let (mut input, mut output, mut reporter) = (MemoryInput::new(""), MemoryOutput::default(), MemoryReporter::default());
+        let mut io = VmIo { input: &mut input, output: &mut output, reporter: &mut reporter };
+        let mut host = HostRegistry::with_builtins();
+        // parse_ir leaves registry ids in CallHost.
+        let host_ids: Vec<usize> = (0..host.functions().count()).collect();
+        let mut machine = Machine { calls, config, source_map, host: &mut host, host_ids: &host_ids, hook: None };
+        return run_bytecode(&mut io, &mut machine, function, parameters);
+    }
 
     #[test]
     fn ir_token() {
@@ -1334,6 +2111,11 @@ mod ir_tests {
         assert!(matches!(lex_ir_token("]are"), (Some(IRTok::RBrace), "are")));
         assert!(matches!(lex_ir_token(",are"), (Some(IRTok::Comma), "are")));
 
+        assert!(matches!(lex_ir_token("-5,"), (Some(IRTok::Num(-5)), ",")));
+        assert!(matches!(lex_ir_token("-2147483648\n"), (Some(IRTok::Num(i32::MIN)), "\n")));
+        assert!(matches!(lex_ir_token("2147483648\n"), (Some(IRTok::InvalidNumber(_)), "\n")));
+        assert!(matches!(lex_ir_token("-x\n"), (Some(IRTok::Var(_)), "\n")));
+
         let code = "; This is a comment\n%mov";
         assert!(matches!(lex_ir_token(code), (Some(IRTok::EndInstr), "%mov")));
     }
@@ -1341,57 +2123,299 @@ mod ir_tests {
     #[test]
     fn ir_lex() {
         {
-            let toks = lex_ir("%add a, b, c\n");
+            let toks = lex_ir("%add a, b, c\n").ok().unwrap();
             assert!(toks.len() == 7);
-            assert!(matches!(toks[0], IRTok::Add));
-            assert!(matches!(toks[1], IRTok::Var(_)));
-            assert!(matches!(toks[2], IRTok::Comma));
-            assert!(matches!(toks[3], IRTok::Var(_)));
-            assert!(matches!(toks[4], IRTok::Comma));
-            assert!(matches!(toks[5], IRTok::Var(_)));
-            assert!(matches!(toks[6], IRTok::EndInstr));
+            assert!(matches!(toks[0].tok, IRTok::Add));
+            assert!(matches!(toks[1].tok, IRTok::Var(_)));
+            assert!(matches!(toks[2].tok, IRTok::Comma));
+            assert!(matches!(toks[3].tok, IRTok::Var(_)));
+            assert!(matches!(toks[4].tok, IRTok::Comma));
+            assert!(matches!(toks[5].tok, IRTok::Var(_)));
+            assert!(matches!(toks[6].tok, IRTok::EndInstr));
         }
 
         {
-            let toks = lex_ir("%func main,,,\n");
+            let toks = lex_ir("%func main,,,\n").ok().unwrap();
             assert!(toks.len() == 6);
-            assert!(matches!(toks[0], IRTok::Func));
-            assert!(matches!(toks[1], IRTok::Var(_)));
-            assert!(matches!(toks[2], IRTok::Comma));
-            assert!(matches!(toks[3], IRTok::Comma));
-            assert!(matches!(toks[4], IRTok::Comma));
-            assert!(matches!(toks[5], IRTok::EndInstr));
+            assert!(matches!(toks[0].tok, IRTok::Func));
+            assert!(matches!(toks[1].tok, IRTok::Var(_)));
+            assert!(matches!(toks[2].tok, IRTok::Comma));
+            assert!(matches!(toks[3].tok, IRTok::Comma));
+            assert!(matches!(toks[4].tok, IRTok::Comma));
+            assert!(matches!(toks[5].tok, IRTok::EndInstr));
         }
 
         {
-            let toks = lex_ir("%func,main,,,\n");
+            let toks = lex_ir("%func,main,,,\n").ok().unwrap();
             assert!(toks.len() == 7);
-            assert!(matches!(toks[0], IRTok::Func));
-            assert!(matches!(toks[1], IRTok::Comma));
-            assert!(matches!(toks[2], IRTok::Var(_)));
-            assert!(matches!(toks[3], IRTok::Comma));
-            assert!(matches!(toks[4], IRTok::Comma));
-            assert!(matches!(toks[5], IRTok::Comma));
-            assert!(matches!(toks[6], IRTok::EndInstr));
+            assert!(matches!(toks[0].tok, IRTok::Func));
+            assert!(matches!(toks[1].tok, IRTok::Comma));
+            assert!(matches!(toks[2].tok, IRTok::Var(_)));
+            assert!(matches!(toks[3].tok, IRTok::Comma));
+            assert!(matches!(toks[4].tok, IRTok::Comma));
+            assert!(matches!(toks[5].tok, IRTok::Comma));
+            assert!(matches!(toks[6].tok, IRTok::EndInstr));
         }
 
         {
-            let toks = lex_ir("%mov [arr+0], 100\n");
+            let toks = lex_ir("%mov [arr+0], 100\n").ok().unwrap();
             assert!(toks.len() == 9);
-            assert!(matches!(toks[0], IRTok::Mov));
-            assert!(matches!(toks[1], IRTok::LBrace));
-            assert!(matches!(toks[2], IRTok::Var(_)));
-            assert!(matches!(toks[3], IRTok::Plus));
-            assert!(matches!(toks[4], IRTok::Num(0)));
-            assert!(matches!(toks[5], IRTok::RBrace));
-            assert!(matches!(toks[6], IRTok::Comma));
-            assert!(matches!(toks[7], IRTok::Num(100)));
-            assert!(matches!(toks[8], IRTok::EndInstr));
+            assert!(matches!(toks[0].tok, IRTok::Mov));
+            assert!(matches!(toks[1].tok, IRTok::LBrace));
+            assert!(matches!(toks[2].tok, IRTok::Var(_)));
+            assert!(matches!(toks[3].tok, IRTok::Plus));
+            assert!(matches!(toks[4].tok, IRTok::Num(0)));
+            assert!(matches!(toks[5].tok, IRTok::RBrace));
+            assert!(matches!(toks[6].tok, IRTok::Comma));
+            assert!(matches!(toks[7].tok, IRTok::Num(100)));
+            assert!(matches!(toks[8].tok, IRTok::EndInstr));
         }
     }
+
+    #[test]
+    fn ir_error_positions() {
+        let toks = lex_ir("%func main()\n    %int a\n  %call a, f(1)\n").ok().unwrap();
+        assert!(toks[5].line == 2 && toks[5].column == 5);
+        assert!(toks[11].line == 3 && toks[11].column == 12);
+
+        let code = "%func main()\n    %int a\n\n    %call a, foo(1)\n%endfunc\n";
+        let e = parse_ir(&lex_ir(code).ok().unwrap(), &mut 0, &HostRegistry::with_builtins()).err().unwrap();
+        assert!(e.line == 4 && e.column == 14 && e.message.contains("Undeclared function"));
+
+        let code = "%func f(%int x)\n%endfunc\n%func main()\n    %int a\n    %call a, f()\n%endfunc\n";
+        let e = parse_ir(&lex_ir(code).ok().unwrap(), &mut 0, &HostRegistry::with_builtins()).err().unwrap();
+        assert!(e.line == 5 && e.column == 14 && e.message.contains("Invalid parameter passing"));
+
+        let code = "%func main()\n    %int a\n    %jmp :done\n    %branch_if a, :nowhere\n:done\n%endfunc\n";
+        let e = parse_ir(&lex_ir(code).ok().unwrap(), &mut 0, &HostRegistry::with_builtins()).err().unwrap();
+        assert!(e.line == 4 && e.column == 19 && e.message.contains("invalid label"));
+
+        let bytecode = parse_ir(&lex_ir("%func main()\n\n    %int a\n    %mov a, 1\n%endfunc\n").ok().unwrap(), &mut 0, &HostRegistry::with_builtins()).ok().unwrap();
+        assert!(bytecode[0].lines == vec![3, 4, 5]);
+
+        let code = "%func main()\n    %int a\n    %add a, 1, 99999999999\n%endfunc\n";
+        let e = lex_ir(code).err().unwrap();
+        assert!(e.line == 3 && e.column == 16 && e.message.contains("'99999999999' does not fit"));
+    }
+
+    #[test]
+    fn ir_frame_slots() {
+        let code = "%func f(%int n, %int m)\n    %int[] a, 4\n    %int x\n    %int[] b, 2\n    %mov [b + 1], m\n    %mov x, [b + 1]\n    %add x, x, n\n    %ret x\n%endfunc\n%func main()\n%endfunc\n";
+        let bytecode = parse_ir(&lex_ir(code).ok().unwrap(), &mut 0, &HostRegistry::with_builtins()).ok().unwrap();
+        let f = &bytecode[0];
+        assert!(f.int_slots == 3 && f.array_lengths == vec![4, 2]);
+        assert!(matches!(f.variables.get("m"), Some(VariableType::IntVar(1))));
+        assert!(matches!(f.variables.get("x"), Some(VariableType::IntVar(2))));
+        assert!(matches!(f.variables.get("b"), Some(VariableType::ArrayVar(1))));
+        assert!(matches!(run_silent(f, &bytecode, &[2, 5], &VmConfig::default(), None), Ok(7)));
+    }
+
+    #[test]
+    fn ir_call_stack() {
+        let code = "%func fib(%int n)\n    %int c\n    %int a\n    %int b\n    %lt c, n, 2\n    %branch_ifn c, :rec\n    %ret n\n:rec\n    %sub n, n, 1\n    %call a, fib(n)\n    %sub n, n, 1\n    %call b, fib(n)\n    %add a, a, b\n    %ret a\n%endfunc\n%func down(%int n)\n    %int r\n    %add n, n, 1\n    %call r, down(n)\n    %ret r\n%endfunc\n%func main()\n%endfunc\n";
+        let bytecode = parse_ir(&lex_ir(code).ok().unwrap(), &mut 0, &HostRegistry::with_builtins()).ok().unwrap();
+        let config = VmConfig::default();
+        assert!(matches!(run_silent(&bytecode[0], &bytecode, &[15], &config, None), Ok(610)));
+
+        // unbounded recursion stops at the configured depth instead of aborting the process.
+        let config = VmConfig { max_call_depth: 50, ..VmConfig::default() };
+        let e = run_silent(&bytecode[1], &bytecode, &[0], &config, None).err().unwrap();
+        assert!(matches!(&e.kind, RuntimeErrorKind::StackOverflow { function, .. } if function == "down"));
+        assert!(e.backtrace.len() == 50);
+        assert!(e.to_string() == "Runtime Error. Stack overflow in function 'down' (maximum call depth 50).\n    at down, IR line 19 [x50]");
+    }
+
+    #[test]
+    fn ir_resource_limits() {
+        let code = "%func main()\n:loop\n    %jmp :loop\n%endfunc\n%func big()\n    %int[] a, 2000000000\n%endfunc\n%func deep(%int n)\n    %int[] a, 1000\n    %int r\n    %branch_if n, :done\n    %call r, deep(1)\n:done\n    %ret 0\n%endfunc\n";
+        let bytecode = parse_ir(&lex_ir(code).ok().unwrap(), &mut 0, &HostRegistry::with_builtins()).ok().unwrap();
+
+        let config = VmConfig { max_instructions: Some(1000), ..VmConfig::default() };
+        let e = run_silent(&bytecode[0], &bytecode, &[], &config, None).err().unwrap();
+        assert!(matches!(e.kind, RuntimeErrorKind::InstructionLimit(1000)) && e.backtrace[0].function == "main");
+
+        let config = VmConfig { time_limit: Some(Duration::from_millis(10)), ..VmConfig::default() };
+        let e = run_silent(&bytecode[0], &bytecode, &[], &config, None).err().unwrap();
+        assert!(e.to_string().starts_with("Runtime Error. Time limit reached. The program did not finish within 10 ms."));
+
+        // waiting for input does not count towards the time limit.
+        struct SlowInput;
+        impl InputSource for SlowInput {
+            fn read_line(&mut self, buf: &mut String) -> std::io::Result<usize> {
+                std::thread::sleep(Duration::from_millis(100));
+                buf.push_str("3000\n");
+                Ok(5)
+            }
+        }
+        let code = "%func main()\n    %int n\n    %int c\n    %input n\n:loop\n    %sub n, n, 1\n    %neq c, n, 0\n    %branch_if c, :loop\n    %input n\n    %ret n\n%endfunc\n";
+        let program = parse_program(code, &HostRegistry::new()).ok().unwrap();
+        let (mut output, mut reporter) = (MemoryOutput::default(), MemoryReporter::default());
+        let mut io = VmIo { input: &mut SlowInput, output: &mut output, reporter: &mut reporter };
+        let config = VmConfig { time_limit: Some(Duration::from_millis(50)), ..VmConfig::default() };
+        assert!(matches!(program.run(&config, &mut io, &mut HostRegistry::new()), Ok(3000)));
+
+        // the array is refused before it is allocated.
+        let e = run_silent(&bytecode[1], &bytecode, &[], &VmConfig::default(), None).err().unwrap();
+        assert!(matches!(e.kind, RuntimeErrorKind::MemoryLimit { needed: 8_000_000_000, limit: 268_435_456, .. }));
+
+        // the arrays of every live frame count, and are given back on return.
+        let config = VmConfig { max_array_memory: 7999, ..VmConfig::default() };
+        assert!(matches!(run_silent(&bytecode[2], &bytecode, &[1], &config, None), Ok(0)));
+        let e = run_silent(&bytecode[2], &bytecode, &[0], &config, None).err().unwrap();
+        assert!(matches!(&e.kind, RuntimeErrorKind::MemoryLimit { function, needed: 8000, .. } if function == "deep"));
+        assert!(e.backtrace.len() == 1 && e.backtrace[0].ir_line == 12);
+    }
+
+    #[test]
+    fn ir_overflow_modes() {
+        let code = "%func main()\n    %int x\n    %int y\n    %mod y, -2147483648, -1\n    %add x, 2147483647, 1\n    %div x, -2147483648, -1\n    %ret x\n%endfunc\n";
+        let bytecode = parse_ir(&lex_ir(code).ok().unwrap(), &mut 0, &HostRegistry::with_builtins()).ok().unwrap();
+        let run = |overflow| run_silent(&bytecode[0], &bytecode, &[], &VmConfig { overflow, ..VmConfig::default() }, None);
+
+        let e = run(OverflowMode::Trap).err().unwrap();
+        assert!(matches!(e.kind, RuntimeErrorKind::Overflow { symbol: "+", lhs: 2147483647, rhs: 1 }));
+        assert!(e.backtrace[0].ir_line == 5);
+        assert!(matches!(run(OverflowMode::Wrap), Ok(i32::MIN)));
+        assert!(matches!(run(OverflowMode::Saturate), Ok(i32::MAX)));
+
+        assert!(matches!(arithmetic(&MULT, OverflowMode::Wrap, 65536, 65536), Ok(0)));
+        assert!(matches!(arithmetic(&SUB, OverflowMode::Saturate, i32::MIN, 1), Ok(i32::MIN)));
+        assert!(matches!(arithmetic(&DIV, OverflowMode::Trap, i32::MIN, -1), Err(RuntimeErrorKind::Overflow { symbol: "/", .. })));
+    }
+
+    #[test]
+    fn ir_input() {
+        let mut input = MemoryInput::new("1 2\n\n  x -3\t4\n5");
+        let mut reporter = MemoryReporter::default();
+        let mut pending = VecDeque::new();
+        let mut numbers = vec![];
+        while let Ok(num) = read_input(&mut input, &mut reporter, &mut pending, None) {
+            numbers.push(num);
+        }
+        assert!(numbers == vec![1, 2, -3, 4, 5]);
+        assert!(reporter.lines == vec!["User Input Error. 'x' is not a valid number."]);
+        assert!(matches!(read_input(&mut input, &mut reporter, &mut pending, None), Err(RuntimeErrorKind::EndOfInput)));
+        assert!(matches!(read_input(&mut input, &mut reporter, &mut pending, Some(-1)), Ok(-1)));
+    }
+
+    #[test]
+    fn ir_captured_io() {
+        let code = "%func main()\n    %int a\n    %input a\n    %mult a, a, 2\n    %out a\n    %out 7\n    %ret a\n%endfunc\n";
+        let (mut input, mut output, mut reporter) = (MemoryInput::new("21\n"), MemoryOutput::default(), MemoryReporter::default());
+        let mut io = VmIo { input: &mut input, output: &mut output, reporter: &mut reporter };
+        assert!(matches!(execute_ir(code, &VmConfig::default(), None, &mut io, &mut HostRegistry::new(), None), Ok(42)));
+        assert!(output.text() == "42\n7\n");
+        assert!(reporter.lines == vec!["Valid IR. Executing Generated Bytecode...", "Run successful. Exit code 42"]);
+
+        let mut reporter = MemoryReporter::default();
+        let mut io = VmIo { input: &mut input, output: &mut output, reporter: &mut reporter };
+        let result = execute_ir("%func main()\n    %out x\n%endfunc\n", &VmConfig::default(), None, &mut io, &mut HostRegistry::new(), None);
+        assert!(matches!(result, Err(ExecuteError::Ir(_))));
+        assert!(reporter.lines[0] == "***Error. Invalid Bytecode." && reporter.lines.last().unwrap().contains("'x' has not been declared"));
+    }
+
+    #[test]
+    fn ir_host_calls() {
+        let code = "%func main()\n    %int a\n    %call a, abs(-7)\n    %call a, twice(a)\n    %ret a\n%endfunc\n";
+        let mut host = HostRegistry::with_builtins();
+        let e = parse_program(code, &host).err().unwrap();
+        assert!(e.line == 4 && e.message.contains("Undeclared function 'twice'"));
+
+        host.register("twice", 1, |args| if args[0] > 1000 { Err(String::from("too large")) } else { Ok(args[0] * 2) });
+        let program = parse_program(code, &host).ok().unwrap();
+        let (mut input, mut output, mut reporter) = (MemoryInput::new(""), MemoryOutput::default(), MemoryReporter::default());
+        let mut io = VmIo { input: &mut input, output: &mut output, reporter: &mut reporter };
+        assert!(matches!(program.run(&VmConfig::default(), &mut io, &mut host), Ok(14)));
+
+        // running with a registry that lacks the host functions is refused up front.
+        let e = program.run(&VmConfig::default(), &mut io, &mut HostRegistry::new()).err().unwrap();
+        assert!(matches!(&e.kind, RuntimeErrorKind::HostMismatch { function, arity: 1 } if function == "abs"));
+
+        // only the functions called are recorded, any order and any extra functions are fine.
+        assert!(program.host_signature == vec![(String::from("abs"), 1), (String::from("twice"), 1)]);
+        let mut reordered = HostRegistry::new();
+        reordered.register("unused", 2, |_| Ok(0));
+        reordered.register("twice", 1, |args| Ok(args[0] * 2));
+        reordered.register("abs", 1, |args| Ok(args[0].abs()));
+        assert!(matches!(program.run(&VmConfig::default(), &mut io, &mut reordered), Ok(14)));
+        let mut different = HostRegistry::with_builtins();
+        different.register("twice", 2, |args| Ok(args[0] * args[1]));
+        assert!(program.run(&VmConfig::default(), &mut io, &mut different).is_err());
+
+        let e = parse_program("%func main()\n    %int a\n    %call a, abs(1, 2)\n%endfunc\n", &host).err().unwrap();
+        assert!(e.message.contains("Invalid parameter passing to 'abs'"));
+
+        let program = parse_program("%func main()\n    %int a\n    %call a, twice(5000)\n%endfunc\n", &host).ok().unwrap();
+        let e = program.run(&VmConfig::default(), &mut io, &mut host).err().unwrap();
+        assert!(e.to_string().starts_with("Runtime Error. Host function 'twice' failed. too large\n    at main, IR line 3"));
+    }
+
+    #[test]
+    fn ir_runtime_backtrace() {
+        let code = "%func div(%int a, %int b)\n    %int q\n    %div q, a, b\n    %ret q\n%endfunc\n%func main()\n    %int r\n    %call r, div(1, 0)\n    %ret r\n%endfunc\n";
+        let bytecode = parse_ir(&lex_ir(code).ok().unwrap(), &mut 0, &HostRegistry::with_builtins()).ok().unwrap();
+        let e = run_silent(&bytecode[1], &bytecode, &[], &VmConfig::default(), None).err().unwrap();
+        assert!(matches!(e.kind, RuntimeErrorKind::DivideByZero));
+        assert!(e.backtrace == vec![
+            StackEntry { function: String::from("div"), ir_line: 3, source_line: None },
+            StackEntry { function: String::from("main"), ir_line: 8, source_line: None },
+        ]);
+
+        assert!(e.to_string() == "Runtime Error. Attempt to divide by zero.\n    at div, IR line 3\n    called from main, IR line 8");
+
+        // IR generated from a .tt file also reports the source lines.
+        let source = "func div(int a, int b) {\n    return a / b;\n}\nfunc main() {\n    int[2] x;\n    print(div(4, 2));\n    print(div(1, x[0]));\n}\n";
+        let program = crate::parser::parse_program(&crate::lexer::lex(source).ok().unwrap()).ok().unwrap();
+        let (ir, map) = crate::codegen::generate_ir(&program).ok().unwrap();
+        let bytecode = parse_ir(&lex_ir(&ir).ok().unwrap(), &mut 0, &HostRegistry::with_builtins()).ok().unwrap();
+        let e = run_silent(&bytecode[1], &bytecode, &[], &VmConfig::default(), Some(&map)).err().unwrap();
+        assert!(e.backtrace[0].source_line == Some(2) && e.backtrace[1].source_line == Some(7));
+    }
+
+    #[test]
+    fn ir_lex_unicode() {
+        let toks = lex_ir("; größe → ∞ ✓\n%int größe ; é\n%mov größe, 3\n").ok().unwrap();
+        assert!(toks.len() == 9);
+        assert!(matches!(&toks[2].tok, IRTok::Var(v) if v == "größe"));
+        assert!(matches!(&toks[5].tok, IRTok::Var(v) if v == "größe"));
+        assert!(toks[5].line == 3 && toks[5].column == 6);
+
+        // non-breaking spaces separate tokens like ordinary whitespace.
+        assert!(matches!(lex_ir_token("%out\u{a0}x"), (Some(IRTok::Out), "x")));
+        assert!(matches!(lex_ir_token(":δ\n"), (Some(IRTok::Label(_)), "\n")));
+
+        let e = lex_ir("%func main()\n    %int a→b\n").err().unwrap();
+        assert!(e.line == 2 && e.column == 10 && e.message.contains("U+2192"));
+        assert!(lex_ir("%out \u{200b}x\n").is_err());
+    }
+
+    #[test]
+    fn ir_lex_end_of_input() {
+        // every final state of the lexer produces a token instead of panicking.
+        assert!(matches!(lex_ir_token(":done"), (Some(IRTok::Label(_)), "")));
+        assert!(matches!(lex_ir_token("42"), (Some(IRTok::Num(42)), "")));
+        assert!(matches!(lex_ir_token("; trailing comment"), (Some(IRTok::EndInstr), "")));
+        assert!(matches!(lex_ir_token("   "), (None, _)));
+
+        let toks = lex_ir("%func main()\n    %ret 0 ; done\n%endfunc ; last line").ok().unwrap();
+        assert!(matches!(toks.last().unwrap().tok, IRTok::EndInstr));
+        let toks = lex_ir("    %jmp :end\n:end").ok().unwrap();
+        assert!(matches!(&toks.last().unwrap().tok, IRTok::Label(l) if l == ":end"));
+        assert!(lex_ir("%ret 7   \n  \t ").ok().unwrap().len() == 3);
+
+        let e = lex_ir("%func main()\n    %jmp :\n").err().unwrap();
+        assert!(e.line == 2 && e.column == 10 && e.message.contains("label"));
+    }
 }
 
 
+#[derive(Debug)]
+struct IRToken {
+    tok: IRTok,
+    line: usize,
+    column: usize,
+}
+
 #[derive(Debug)]
 enum IRTok {
     // func
@@ -1442,48 +2466,49 @@ enum IRTok {
     EndInstr,
 
     InvalidInstruction(String),
+    InvalidNumber(String),
+    InvalidChar(char),
 
     Num(i32),
     Var(String),
 }
 
 #[derive(Debug, Clone)]
-enum Op {
+pub(crate) enum Op {
     Num(i32),
-    Var(i32),
+    Var(usize),
 }
 
 #[derive(Debug)]
-enum VariableType {
-    IntVar(i32),
-    ArrayVar(i32, i32),
+pub(crate) enum VariableType {
+    IntVar(usize),
+    ArrayVar(usize),
 }
 
 #[derive(Debug)]
-enum MemWrite {
-    IntVar(i32),
-    ArrayWrite(i32, Op),
+pub(crate) enum MemWrite {
+    IntVar(usize),
+    ArrayWrite(usize, Op),
 }
 
 #[derive(Debug)]
-enum MemRead {
-    IntVar(i32),
+pub(crate) enum MemRead {
+    IntVar(usize),
     Number(i32),
-    ArrayRead(i32, Op),
+    ArrayRead(usize, Op),
 }
 
-// TODO: array bounds check.
-fn read_memory(variables: &HashMap<i32, i32>, arrays: &HashMap<i32, Vec<i32>>, read: &MemRead) -> Result<i32, IRError> {
+fn read_memory(frame: &Frame, read: &MemRead) -> Result<i32, RuntimeErrorKind> {
     match read {
-    MemRead::IntVar(id) => Ok(*variables.get(&id).unwrap()),
+    MemRead::IntVar(id) => Ok(frame.ints[*id]),
     MemRead::Number(number) => Ok(*number),
     MemRead::ArrayRead(id, index) => {
-        let array = arrays.get(&id).unwrap();
-        let variable = read_integer_value(&variables, &index);
+        let array = &frame.arrays[*id];
+        let variable = read_integer_value(frame, index);
         if variable >= 0 && (variable as usize) < array.len() {
             Ok(array[variable as usize])
         } else {
-            error(MAX_LINE, format!("Runtime Error: Array out of bounds. Index {}. Array Length {}.", variable, array.len()))
+            Err(RuntimeErrorKind::OutOfBounds { index: variable, len: array.len() })
         }
     }
     }
@@ -1491,38 +2516,41 @@ fn read_memory(variables: &HashMap<i32, i32>, arrays: &HashMap<i32, Vec<i32>>, r
 
 
 #[derive(Debug)]
-enum Bytecode {
+pub(crate) enum Bytecode {
 
     // EndFunc
     End,
-    Label(usize),
+    Label,
 
     // declarations.
-    Int(i32),
-    IntArray(i32, i32),
+    Int(usize),
+    IntArray(usize),
 
     // input/output routines.
     Out(Op),
-    In(i32),
+    In(usize),
 
     // mathematical operators.
     Mov(MemWrite, MemRead),
-    Add(i32, Op, Op),
-    Sub(i32, Op, Op),
-    Mult(i32, Op, Op),
-    Div(i32, Op, Op),
-    Mod(i32, Op, Op),
+    Add(usize, Op, Op),
+    Sub(usize, Op, Op),
+    Mult(usize, Op, Op),
+    Div(usize, Op, Op),
+    Mod(usize, Op, Op),
 
     // calling functions.
-    Call(i32, usize, Vec<Op>),
+    Call(usize, usize, Vec<Op>),
+    // dest, host function, parameters. parse_ir stores the registry id, parse_program turns
+    // it into an index into Program::host_signature, which the VM resolves by name.
+    CallHost(usize, usize, Vec<Op>),
 
     // comparison operators.
-    LessThan(i32, Op, Op),
-    LessEqual(i32, Op, Op),
-    NotEqual(i32, Op, Op),
-    Equal(i32, Op, Op),
-    GreaterEqual(i32, Op, Op),
-    GreaterThan(i32, Op, Op),
+    LessThan(usize, Op, Op),
+    LessEqual(usize, Op, Op),
+    NotEqual(usize, Op, Op),
+    Equal(usize, Op, Op),
+    GreaterEqual(usize, Op, Op),
+    GreaterThan(usize, Op, Op),
     Return(Op),
     Jmp(usize),
     BranchIf(Op, usize),
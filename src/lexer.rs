@@ -0,0 +1,327 @@
+use std::fmt;
+
+use crate::diagnostics::Diagnostic;
+
+// location of a token inside the source file.
+// start/end are byte offsets, line/col are 1-based (col counts characters).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Span {
+    // span starting at self and ending at the end of other.
+    pub fn to(&self, other: Span) -> Span {
+        Span { end: other.end, ..*self }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    // keywords.
+    Func,
+    Return,
+    Int,
+    Print,
+    Read,
+    While,
+    If,
+    Else,
+    Break,
+    Continue,
+
+    // mathematical operators.
+    Plus,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulus,
+    Assign,
+
+    // comparison operators.
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equality,
+    NotEqual,
+
+    // punctuation.
+    LeftParen,
+    RightParen,
+    LeftCurly,
+    RightCurly,
+    LeftBracket,
+    RightBracket,
+    Comma,
+    Semicolon,
+
+    Ident(String),
+    Num(i32),
+
+    End,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+        Token::Func => "func",
+        Token::Return => "return",
+        Token::Int => "int",
+        Token::Print => "print",
+        Token::Read => "read",
+        Token::While => "while",
+        Token::If => "if",
+        Token::Else => "else",
+        Token::Break => "break",
+        Token::Continue => "continue",
+        Token::Plus => "+",
+        Token::Subtract => "-",
+        Token::Multiply => "*",
+        Token::Divide => "/",
+        Token::Modulus => "%",
+        Token::Assign => "=",
+        Token::Less => "<",
+        Token::LessEqual => "<=",
+        Token::Greater => ">",
+        Token::GreaterEqual => ">=",
+        Token::Equality => "==",
+        Token::NotEqual => "!=",
+        Token::LeftParen => "(",
+        Token::RightParen => ")",
+        Token::LeftCurly => "{",
+        Token::RightCurly => "}",
+        Token::LeftBracket => "[",
+        Token::RightBracket => "]",
+        Token::Comma => ",",
+        Token::Semicolon => ";",
+        Token::Ident(ident) => return write!(f, "identifier '{ident}'"),
+        Token::Num(num) => return write!(f, "number '{num}'"),
+        Token::End => "end of file",
+        };
+        write!(f, "'{s}'")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
+// lexes the whole file. the returned vector always ends with Token::End.
+// invalid characters do not stop the lexer, every problem in the file is reported.
+pub fn lex(code: &str) -> Result<Vec<SpannedToken>, Vec<Diagnostic>> {
+    let mut cursor = Cursor { code, pos: 0, line: 1, col: 1 };
+    let mut tokens: Vec<SpannedToken> = vec![];
+    let mut errors: Vec<Diagnostic> = vec![];
+
+    while let Some(c) = cursor.peek() {
+        if c.is_whitespace() {
+            cursor.bump();
+            continue;
+        }
+
+        // comments run until the end of the line.
+        if c == '#' {
+            while let Some(c) = cursor.peek() {
+                if c == '\n' {
+                    break;
+                }
+                cursor.bump();
+            }
+            continue;
+        }
+
+        let start = cursor.mark();
+        cursor.bump();
+        let token = match c {
+        '+' => Token::Plus,
+        '-' => Token::Subtract,
+        '*' => Token::Multiply,
+        '/' => Token::Divide,
+        '%' => Token::Modulus,
+        '(' => Token::LeftParen,
+        ')' => Token::RightParen,
+        '{' => Token::LeftCurly,
+        '}' => Token::RightCurly,
+        '[' => Token::LeftBracket,
+        ']' => Token::RightBracket,
+        ',' => Token::Comma,
+        ';' => Token::Semicolon,
+        '=' => if cursor.eat('=') { Token::Equality } else { Token::Assign },
+        '<' => if cursor.eat('=') { Token::LessEqual } else { Token::Less },
+        '>' => if cursor.eat('=') { Token::GreaterEqual } else { Token::Greater },
+        '!' => {
+            if cursor.eat('=') {
+                Token::NotEqual
+            } else {
+                let message = String::from("invalid character '!'. did you mean '!='?");
+                errors.push(Diagnostic::new("E0101", cursor.span_from(start), message));
+                continue;
+            }
+        }
+
+        '0'..='9' => {
+            while matches!(cursor.peek(), Some(c) if is_ident_char(c)) {
+                cursor.bump();
+            }
+            let span = cursor.span_from(start);
+            let text = &code[span.start..span.end];
+            if !text.chars().all(|c| c.is_ascii_digit()) {
+                let message = format!("invalid number literal '{text}'. identifiers cannot begin with a digit.");
+                errors.push(Diagnostic::new("E0102", span, message));
+                continue;
+            }
+            match text.parse::<i32>() {
+            Ok(num) => Token::Num(num),
+            Err(_) => {
+                let message = format!("number literal '{text}' does not fit in a 32-bit integer.");
+                errors.push(Diagnostic::new("E0103", span, message));
+                continue;
+            }
+            }
+        }
+
+        c if c == '_' || c.is_ascii_alphabetic() => {
+            while matches!(cursor.peek(), Some(c) if is_ident_char(c)) {
+                cursor.bump();
+            }
+            let span = cursor.span_from(start);
+            keyword_or_ident(&code[span.start..span.end])
+        }
+
+        _ => {
+            let message = format!("invalid character '{}'.", c.escape_default());
+            errors.push(Diagnostic::new("E0101", cursor.span_from(start), message));
+            continue;
+        }
+        };
+
+        tokens.push(SpannedToken { token, span: cursor.span_from(start) });
+    }
+
+    let end = cursor.mark();
+    tokens.push(SpannedToken { token: Token::End, span: cursor.span_from(end) });
+
+    if errors.is_empty() {
+        return Ok(tokens);
+    } else {
+        return Err(errors);
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c == '_' || c.is_ascii_alphanumeric()
+}
+
+fn keyword_or_ident(s: &str) -> Token {
+    match s {
+    "func" => Token::Func,
+    "return" => Token::Return,
+    "int" => Token::Int,
+    "print" => Token::Print,
+    "read" => Token::Read,
+    "while" => Token::While,
+    "if" => Token::If,
+    "else" => Token::Else,
+    "break" => Token::Break,
+    "continue" => Token::Continue,
+    _ => Token::Ident(String::from(s)),
+    }
+}
+
+struct Cursor<'a> {
+    code: &'a str,
+    pos: usize,
+    line: usize,
+    col: usize,
+}
+
+impl Cursor<'_> {
+    fn peek(&self) -> Option<char> {
+        self.code[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) {
+        if let Some(c) = self.peek() {
+            self.pos += c.len_utf8();
+            if c == '\n' {
+                self.line += 1;
+                self.col = 1;
+            } else {
+                self.col += 1;
+            }
+        }
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.bump();
+            return true;
+        }
+        return false;
+    }
+
+    fn mark(&self) -> Span {
+        Span { start: self.pos, end: self.pos, line: self.line, col: self.col }
+    }
+
+    fn span_from(&self, start: Span) -> Span {
+        Span { end: self.pos, ..start }
+    }
+}
+
+#[cfg(test)]
+mod lexer_tests {
+    use crate::lexer::*;
+
+    fn tokens(code: &str) -> Vec<Token> {
+        lex(code).ok().unwrap().into_iter().map(|t| t.token).collect()
+    }
+
+    #[test]
+    fn lex_tokens() {
+        let toks = tokens("func main() { int[100] a; }");
+        assert!(toks == vec![
+            Token::Func, Token::Ident(String::from("main")), Token::LeftParen, Token::RightParen,
+            Token::LeftCurly, Token::Int, Token::LeftBracket, Token::Num(100), Token::RightBracket,
+            Token::Ident(String::from("a")), Token::Semicolon, Token::RightCurly, Token::End,
+        ]);
+
+        let toks = tokens("a <= b >= c == d != e < f > g = h % i # comment = 3\n");
+        assert!(matches!(toks[1], Token::LessEqual));
+        assert!(matches!(toks[3], Token::GreaterEqual));
+        assert!(matches!(toks[5], Token::Equality));
+        assert!(matches!(toks[7], Token::NotEqual));
+        assert!(matches!(toks[9], Token::Less));
+        assert!(matches!(toks[11], Token::Greater));
+        assert!(matches!(toks[13], Token::Assign));
+        assert!(matches!(toks[15], Token::Modulus));
+        assert!(toks.len() == 18);
+    }
+
+    #[test]
+    fn lex_spans() {
+        let toks = lex("func main() {\n    int é;\n}").err().unwrap();
+        assert!(toks.len() == 1);
+        assert!(toks[0].span.line == 2 && toks[0].span.col == 9);
+
+        let toks = lex("# café\nwhile i").ok().unwrap();
+        assert!(matches!(toks[0].token, Token::While));
+        assert!(toks[0].span.start == 8 && toks[0].span.line == 2 && toks[0].span.col == 1);
+        assert!(toks[1].span.start == 14 && toks[1].span.end == 15 && toks[1].span.col == 7);
+    }
+
+    #[test]
+    fn lex_errors() {
+        let errors = lex("int a = 99999999999; int 1abc; a ! b @").err().unwrap();
+        assert!(errors.len() == 4);
+        assert!(errors[0].span.col == 9);
+        assert!(errors[0].code == "E0103");
+        assert!(errors[1].code == "E0102" && errors[1].message.contains("1abc"));
+    }
+}
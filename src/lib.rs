@@ -0,0 +1,113 @@
+#![allow(clippy::needless_return)]
+
+// compiler and virtual machine for the .tt language.
+//
+//   .tt source --lexer--> tokens --parser--> ast --semantic/codegen--> IR --interpreter--> run
+//
+// the functions below cover the common uses: compile a .tt file or parse an IR file into
+// an interpreter::Program, then run it with a given input and collect what it printed.
+// the modules stay public for tools that need a single stage.
+//
+// 'host' is the set of native functions programs may call, usually HostRegistry::with_builtins().
+
+pub mod ast;
+pub mod bytecode_file;
+pub mod codegen;
+pub mod coverage;
+pub mod debugger;
+pub mod diagnostics;
+pub mod host;
+pub mod interpreter;
+pub mod lexer;
+pub mod parser;
+pub mod profile;
+pub mod semantic;
+pub mod trace;
+pub mod vm_io;
+
+use diagnostics::Diagnostic;
+use host::HostRegistry;
+use interpreter::{Program, RuntimeError, VmConfig};
+use lexer::Span;
+
+// compiles a .tt source into IR, with the map from IR lines back to source lines.
+pub fn compile_to_ir(source: &str, host: &HostRegistry) -> Result<(String, codegen::SourceMap), Vec<Diagnostic>> {
+    let tokens = lexer::lex(source)?;
+    let program = parser::parse_program(&tokens)?;
+    semantic::check_program(&program, host)?;
+    return codegen::generate_ir(&program).map_err(|e| vec![e]);
+}
+
+// compiles a .tt source into a program whose runtime errors point at the source lines.
+pub fn compile(source: &str, host: &HostRegistry) -> Result<Program, Vec<Diagnostic>> {
+    let (ir, source_map) = compile_to_ir(source, host)?;
+    match interpreter::parse_program(&ir, host) {
+    Ok(program) => Ok(program.with_source_map(Some(source_map))),
+    // the IR comes from codegen, so this is a bug in the compiler and not in the source.
+    Err(e) => Err(vec![Diagnostic::new("E0401", Span::default(), format!("generated IR is invalid: {e}"))]),
+    }
+}
+
+// parses a hand-written IR file.
+pub fn parse_ir(code: &str, host: &HostRegistry) -> Result<Program, interpreter::IRError> {
+    return interpreter::parse_program(code, host);
+}
+
+pub struct RunResult {
+    // main's return value, or the error that stopped the program.
+    pub result: Result<i32, RuntimeError>,
+
+    // every number passed to '%out'.
+    pub output: Vec<i32>,
+
+    // status lines the VM reported along the way, such as rejected input.
+    pub messages: Vec<String>,
+}
+
+// runs the program with 'input' as the text '%input' reads from.
+pub fn run(program: &Program, input: &str, config: &VmConfig, host: &mut HostRegistry) -> RunResult {
+    let mut input = vm_io::MemoryInput::new(input);
+    let mut output = vm_io::MemoryOutput::default();
+    let mut reporter = vm_io::MemoryReporter::default();
+    let mut io = vm_io::VmIo { input: &mut input, output: &mut output, reporter: &mut reporter };
+    let result = program.run(config, &mut io, host);
+    return RunResult { result, output: output.values, messages: reporter.lines };
+}
+
+#[cfg(test)]
+mod lib_tests {
+    use crate::interpreter::RuntimeErrorKind;
+    use crate::*;
+
+    #[test]
+    fn compile_and_run() {
+        let mut host = HostRegistry::with_builtins();
+        let program = compile("func main() {\n    int a;\n    read(a);\n    print(a * 2);\n    return abs(a);\n}\n", &host).ok().unwrap();
+        let run1 = run(&program, "x -21", &VmConfig::default(), &mut host);
+        assert!(matches!(run1.result, Ok(21)));
+        assert!(run1.output == vec![-42]);
+        assert!(run1.messages == vec!["User Input Error. 'x' is not a valid number."]);
+
+        // the same program can run again with other input.
+        let run2 = run(&program, "", &VmConfig::default(), &mut host);
+        let e = run2.result.err().unwrap();
+        assert!(matches!(e.kind, RuntimeErrorKind::EndOfInput) && e.backtrace[0].source_line == Some(3));
+
+        let errors = compile("func main() { x = 1; }", &host).err().unwrap();
+        assert!(errors[0].code == "E0301");
+    }
+
+    #[test]
+    fn parse_and_run_ir() {
+        let mut host = HostRegistry::new();
+        let program = parse_ir("%func main()\n    %out 5\n    %ret 1\n%endfunc\n", &host).ok().unwrap();
+        assert!(program.functions()[0].name() == "main" && program.functions()[0].len() == 2);
+        let result = run(&program, "", &VmConfig::default(), &mut host);
+        assert!(matches!(result.result, Ok(1)) && result.output == vec![5]);
+
+        let e = parse_ir("%func main()\n    %out y\n%endfunc\n", &host).err().unwrap();
+        assert!(e.line() == Some(2) && e.message().contains("'y'"));
+        let e = parse_ir("%func f()\n%endfunc\n", &host).err().unwrap();
+        assert!(e.line().is_none() && e.message().contains("main"));
+    }
+}
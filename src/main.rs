@@ -1,27 +1,166 @@
-use std::{env, fs};
+#![allow(clippy::needless_return)]
 
-mod interpreter;
+use std::time::Duration;
+use std::{env, fs, process};
+
+use rustcompiler::{ast, bytecode_file, codegen, coverage, debugger, diagnostics, host, interpreter, lexer, parser, profile, trace, vm_io};
+
+const USAGE: &str = "\
+usage: rustcompiler <command> <file> [options]
+
+commands:
+    lex <file.tt>                   print the tokens of a .tt file
+    parse <file.tt>                 print the AST of a .tt file
+    ir <file.tt> [-o <file.ir>]     print the generated IR, or write it to a file
+    run <file.tt>                   compile and execute a .tt file
+    run-ir <file.ir>                execute a hand-written IR file
+    debug <file.tt>                 compile a .tt file and run it in the IR debugger
+    debug-ir <file.ir>              run an IR file in the debugger
+    assemble <file.ir> [-o <file.ttbc>]
+                                    check an IR file and save it as binary bytecode, next
+                                    to the IR file unless '-o' is given
+    run-bc <file.ttbc>              execute a bytecode file saved by 'assemble'
+    coverage <file.tt> --data <file> [--ir] [--html] [-o <file>]
+                                    list the .tt file (or its IR with '--ir') with the
+                                    coverage recorded by '--coverage', as text or html
+    coverage-ir <file.ir> --data <file> [--html] [-o <file>]
+                                    the same for an IR file
+
+options for run, run-ir, run-bc, debug and debug-ir:
+    --input <file>                  read '%input' numbers from a file instead of stdin
+    -o, --output <file>             write the program output to a file instead of stdout
+    --max-call-depth <n>            number of nested calls allowed before a stack overflow
+                                    error (default 100000)
+    --overflow <trap|wrap|saturate> what integer overflow does: stop with a runtime error
+                                    (default), wrap around, or clamp to the i32 range
+    --eof-value <n>                 value '%input' reads once the input has ended, instead
+                                    of stopping with a runtime error
+    --exit-code <return|zero>       exit with main's return value (default), or with 0
+                                    whenever the program runs to completion
+    --max-instructions <n>          stop with a runtime error after n executed instructions
+    --time-limit <ms>               stop with a runtime error once the program ran for ms
+                                    milliseconds (not while it waits for input)
+    --max-array-memory <MiB>        memory all live arrays may use together (default 256)
+    --trace[=<file>]                log every executed instruction with the values it reads
+                                    and writes, to stderr or a file
+    --trace-func <name>             only trace this function, can be given several times
+    --trace-limit <n>               stop tracing after n lines, the program keeps running
+    --profile[=<file>]              count the executed instructions per function, label,
+                                    call and instruction kind, report to stderr or a file
+    --profile-folded <file>         write the counts per call stack in the folded format
+                                    flamegraph tools read
+    --coverage <file>               record which instructions and branches ran, added to
+                                    the counts already in the file (not for run-bc)
+
+built-in functions, callable from .tt files and with '%call':
+    abs(x)                          absolute value of x
+    rand_seeded(seed)               a non-negative pseudo random number that only depends on seed
+    time_ms()                       milliseconds since the program started running
+
+'rustcompiler <file.tt>' is the same as 'rustcompiler run <file.tt>'.
+stdout only carries what the command produces, such as the program's output. compile errors,
+the VM's status lines and every other error go to stderr.
+the debugger reads its commands from stdin, type 'help' at its prompt for the list.
+use '--input' when the program reads input too.
+
+exit status:
+    0-255   the program ran, the low 8 bits of main's return value (or 0 with '--exit-code zero')
+    64      invalid commandline arguments
+    65      the .tt file has errors
+    66      the IR or the bytecode file is invalid
+    70      the program stopped with a runtime error
+    74      a file or the program input/output could not be read or written
+
+a program whose main returns one of these values can not be told apart from the error,
+use '--exit-code zero' when that matters.";
+
+// exit statuses, following the BSD sysexits.h values where one fits.
+const EXIT_USAGE: i32 = 64;
+const EXIT_COMPILE_ERROR: i32 = 65;
+const EXIT_IR_ERROR: i32 = 66;
+const EXIT_RUNTIME_ERROR: i32 = 70;
+const EXIT_IO_ERROR: i32 = 74;
+
+struct Options {
+    filename: String,
+    input: Option<String>,
+
+    // the IR file for 'ir', the bytecode file for 'assemble', the program output for the
+    // commands that run a program, the listing for 'coverage'.
+    output: Option<String>,
+    vm: interpreter::VmConfig,
+
+    // exit with 0 instead of main's return value.
+    exit_zero: bool,
+
+    // --trace, with the file after '=' or None for stderr.
+    trace: Option<Option<String>>,
+    trace_functions: Vec<String>,
+    trace_limit: Option<usize>,
+
+    // --profile, like --trace, and --profile-folded.
+    profile: Option<Option<String>>,
+    profile_folded: Option<String>,
+
+    // the data file --coverage records into, and the one 'coverage' lists with --data.
+    coverage: Option<String>,
+    data: Option<String>,
+    listing_ir: bool,
+    html: bool,
+}
 
 fn main() {
     // get commandline arguments.
     let args: Vec<String> = env::args().collect();
+    process::exit(run_command(&args));
+}
+
+// returns the process exit status.
+fn run_command(args: &[String]) -> i32 {
     if args.len() == 1 {
-        println!("Please provide an input file.");
-        return;
+        eprintln!("Please provide an input file.");
+        eprintln!("{USAGE}");
+        return EXIT_USAGE;
+    }
+
+    let (command, rest) = match args[1].as_str() {
+    "lex" | "parse" | "ir" | "run" | "run-ir" | "debug" | "debug-ir" | "coverage" | "coverage-ir" | "assemble" | "run-bc" => (args[1].as_str(), &args[2..]),
+    "help" | "-h" | "--help" => {
+        println!("{USAGE}");
+        return 0;
     }
+    _ => ("run", &args[1..]),
+    };
+
+    let options = match parse_options(command, rest) {
+    Some(options) => options,
+    None => {
+        eprintln!("{USAGE}");
+        return EXIT_USAGE;
+    }
+    };
+
+    let filename = &options.filename;
 
-    if args.len() > 2 {
-        println!("Too many commandline arguments.");
-        return;
+    // bytecode is binary, it is loaded here instead of read as text.
+    if command == "run-bc" {
+        let program = match fs::read(filename).map_err(|e| (EXIT_IO_ERROR, e.to_string()))
+            .and_then(|bytes| bytecode_file::load(&bytes).map_err(|e| (EXIT_IR_ERROR, e.to_string()))) {
+        Ok(program) => program,
+        Err((status, error)) => {
+            eprintln!("**Error. File \"{}\": {}", filename, error);
+            return status;
+        }
+        };
+        return execute(&options, Runnable::Bytecode(program), false);
     }
 
     // read the entire file.
-    let filename = &args[1];
     let result = fs::read_to_string(filename);
     let code = match result {
         Err(error) => {
-            println!("**Error. File \"{}\": {}", filename, error);
-            return;
+            eprintln!("**Error. File \"{}\": {}", filename, error);
+            return EXIT_IO_ERROR;
         }
 
         Ok(code) => {
@@ -29,5 +168,504 @@ fn main() {
         }
     };
 
-    // Start Here!!
+    match command {
+    "lex" => {
+        let Some(tokens) = lex(filename, &code) else { return EXIT_COMPILE_ERROR };
+        for t in &tokens {
+            println!("{}:{}\t{}", t.span.line, t.span.col, t.token);
+        }
+        return 0;
+    }
+
+    "parse" => {
+        let Some(program) = parse(filename, &code) else { return EXIT_COMPILE_ERROR };
+        println!("{:#?}", program);
+        return 0;
+    }
+
+    "ir" => {
+        let Some((ir, _)) = compile(filename, &code) else { return EXIT_COMPILE_ERROR };
+        return write_output(&options.output, &ir);
+    }
+
+    "coverage" | "coverage-ir" => {
+        let data = options.data.as_ref().unwrap();
+        let coverage = match fs::read_to_string(data).map_err(|e| e.to_string()).and_then(|text| coverage::Coverage::from_data(&text)) {
+        Ok(coverage) => coverage,
+        Err(error) => {
+            eprintln!("**Error. File \"{}\": {}", data, error);
+            return EXIT_IO_ERROR;
+        }
+        };
+
+        // the IR is generated again, the fingerprint in the data tells whether it still matches.
+        let (ir, source_map) = if command == "coverage" {
+            let Some((ir, source_map)) = compile(filename, &code) else { return EXIT_COMPILE_ERROR };
+            (ir, Some(source_map))
+        } else {
+            (code.clone(), None)
+        };
+        if !coverage.is_for(&ir) {
+            eprintln!("**Error. File \"{}\": the coverage data is for a different program, or another version of it.", data);
+            return EXIT_IO_ERROR;
+        }
+
+        let listing = match &source_map {
+        Some(source_map) if !options.listing_ir => coverage.source_listing(&code, source_map),
+        _ => coverage.ir_listing(&ir),
+        };
+        let listing = if options.html { listing.html(filename) } else { listing.text() };
+        return write_output(&options.output, &listing);
+    }
+
+    "run" | "debug" => {
+        let Some((ir, source_map)) = compile(filename, &code) else { return EXIT_COMPILE_ERROR };
+        return execute(&options, Runnable::Ir(&ir, Some(source_map)), command == "debug");
+    }
+
+    "run-ir" | "debug-ir" => {
+        return execute(&options, Runnable::Ir(&code, None), command == "debug-ir");
+    }
+
+    "assemble" => {
+        // the host functions are the ones 'run-bc' provides.
+        let program = match rustcompiler::parse_ir(&code, &host::HostRegistry::with_builtins()) {
+        Ok(program) => program,
+        Err(error) => {
+            eprintln!("**Error. File \"{}\": {}", filename, error);
+            return EXIT_IR_ERROR;
+        }
+        };
+        let path = match &options.output {
+        Some(path) => path.clone(),
+        None => String::from(std::path::Path::new(filename).with_extension("ttbc").to_string_lossy()),
+        };
+        if let Err(error) = fs::write(&path, bytecode_file::save(&program)) {
+            eprintln!("**Error. File \"{}\": {}", path, error);
+            return EXIT_IO_ERROR;
+        }
+        return 0;
+    }
+
+    _ => unreachable!(),
+    }
+}
+
+fn runs_program(command: &str) -> bool {
+    matches!(command, "run" | "run-ir" | "run-bc" | "debug" | "debug-ir")
+}
+
+fn parse_options(command: &str, args: &[String]) -> Option<Options> {
+    let mut filename: Option<String> = None;
+    let mut input: Option<String> = None;
+    let mut output: Option<String> = None;
+    let mut vm = interpreter::VmConfig::default();
+    let mut exit_zero = false;
+    let mut trace: Option<Option<String>> = None;
+    let mut trace_functions: Vec<String> = vec![];
+    let mut trace_limit: Option<usize> = None;
+    let mut profile: Option<Option<String>> = None;
+    let mut profile_folded: Option<String> = None;
+    let mut coverage: Option<String> = None;
+    let mut data: Option<String> = None;
+    let mut listing_ir = false;
+    let mut html = false;
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        match arg {
+        "-o" | "--output" if command == "ir" || command == "assemble" || command.starts_with("coverage") || runs_program(command) => {
+            i += 1;
+            match args.get(i) {
+            Some(path) => output = Some(path.clone()),
+            None => {
+                eprintln!("Missing file name after '{arg}'.");
+                return None;
+            }
+            }
+        }
+
+        "--input" if runs_program(command) => {
+            i += 1;
+            match args.get(i) {
+            Some(path) => input = Some(path.clone()),
+            None => {
+                eprintln!("Missing file name after '{arg}'.");
+                return None;
+            }
+            }
+        }
+
+        "--max-call-depth" if runs_program(command) => {
+            i += 1;
+            match args.get(i).and_then(|n| n.parse::<usize>().ok()) {
+            Some(depth) if depth > 0 => vm.max_call_depth = depth,
+            _ => {
+                eprintln!("'{arg}' needs a positive number.");
+                return None;
+            }
+            }
+        }
+
+        "--max-instructions" if runs_program(command) => {
+            i += 1;
+            match args.get(i).and_then(|n| n.parse::<u64>().ok()) {
+            Some(limit) => vm.max_instructions = Some(limit),
+            None => {
+                eprintln!("'{arg}' needs a number.");
+                return None;
+            }
+            }
+        }
+
+        "--time-limit" if runs_program(command) => {
+            i += 1;
+            match args.get(i).and_then(|n| n.parse::<u64>().ok()) {
+            Some(ms) => vm.time_limit = Some(Duration::from_millis(ms)),
+            None => {
+                eprintln!("'{arg}' needs a number of milliseconds.");
+                return None;
+            }
+            }
+        }
+
+        "--max-array-memory" if runs_program(command) => {
+            i += 1;
+            match args.get(i).and_then(|n| n.parse::<usize>().ok()) {
+            Some(mib) => vm.max_array_memory = mib.saturating_mul(1024 * 1024),
+            None => {
+                eprintln!("'{arg}' needs a number of MiB.");
+                return None;
+            }
+            }
+        }
+
+        "--eof-value" if runs_program(command) => {
+            i += 1;
+            match args.get(i).and_then(|n| n.parse::<i32>().ok()) {
+            Some(value) => vm.eof_value = Some(value),
+            None => {
+                eprintln!("'{arg}' needs a number.");
+                return None;
+            }
+            }
+        }
+
+        "--exit-code" if runs_program(command) => {
+            i += 1;
+            exit_zero = match args.get(i).map(|mode| mode.as_str()) {
+            Some("return") => false,
+            Some("zero") => true,
+            _ => {
+                eprintln!("'{arg}' needs 'return' or 'zero'.");
+                return None;
+            }
+            };
+        }
+
+        "--overflow" if runs_program(command) => {
+            i += 1;
+            vm.overflow = match args.get(i).map(|mode| mode.as_str()) {
+            Some("trap") => interpreter::OverflowMode::Trap,
+            Some("wrap") => interpreter::OverflowMode::Wrap,
+            Some("saturate") => interpreter::OverflowMode::Saturate,
+            _ => {
+                eprintln!("'{arg}' needs one of 'trap', 'wrap' or 'saturate'.");
+                return None;
+            }
+            };
+        }
+
+        "--trace" if runs_program(command) => trace = Some(None),
+        _ if arg.starts_with("--trace=") && runs_program(command) => trace = Some(Some(String::from(&arg["--trace=".len()..]))),
+
+        "--trace-func" if runs_program(command) => {
+            i += 1;
+            match args.get(i) {
+            Some(name) => trace_functions.push(name.clone()),
+            None => {
+                eprintln!("Missing function name after '{arg}'.");
+                return None;
+            }
+            }
+        }
+
+        "--trace-limit" if runs_program(command) => {
+            i += 1;
+            match args.get(i).and_then(|n| n.parse::<usize>().ok()) {
+            Some(limit) => trace_limit = Some(limit),
+            None => {
+                eprintln!("'{arg}' needs a number.");
+                return None;
+            }
+            }
+        }
+
+        "--profile" if runs_program(command) => profile = Some(None),
+        _ if arg.starts_with("--profile=") && runs_program(command) => profile = Some(Some(String::from(&arg["--profile=".len()..]))),
+
+        "--profile-folded" if runs_program(command) => {
+            i += 1;
+            match args.get(i) {
+            Some(path) => profile_folded = Some(path.clone()),
+            None => {
+                eprintln!("Missing file name after '{arg}'.");
+                return None;
+            }
+            }
+        }
+
+        "--coverage" if runs_program(command) => {
+            i += 1;
+            match args.get(i) {
+            Some(path) => coverage = Some(path.clone()),
+            None => {
+                eprintln!("Missing file name after '{arg}'.");
+                return None;
+            }
+            }
+        }
+
+        "--data" if command.starts_with("coverage") => {
+            i += 1;
+            match args.get(i) {
+            Some(path) => data = Some(path.clone()),
+            None => {
+                eprintln!("Missing file name after '{arg}'.");
+                return None;
+            }
+            }
+        }
+
+        "--ir" if command == "coverage" => listing_ir = true,
+        "--html" if command.starts_with("coverage") => html = true,
+
+        _ if arg.starts_with('-') => {
+            eprintln!("Unknown option '{arg}' for '{command}'.");
+            return None;
+        }
+
+        _ => {
+            if filename.is_some() {
+                eprintln!("Too many commandline arguments.");
+                return None;
+            }
+            filename = Some(String::from(arg));
+        }
+        }
+        i += 1;
+    }
+
+    if trace.is_none() && (!trace_functions.is_empty() || trace_limit.is_some()) {
+        eprintln!("'--trace-func' and '--trace-limit' need '--trace'.");
+        return None;
+    }
+
+    // coverage is recorded against the IR text, which a bytecode file does not keep.
+    if command == "run-bc" && coverage.is_some() {
+        eprintln!("'--coverage' needs the IR, use 'run-ir' instead of 'run-bc'.");
+        return None;
+    }
+
+    if command.starts_with("coverage") && data.is_none() {
+        eprintln!("'{command}' needs the coverage data file, '--data <file>'.");
+        return None;
+    }
+
+    match filename {
+    Some(filename) => Some(Options {
+        filename, input, output, vm, exit_zero, trace, trace_functions, trace_limit, profile, profile_folded, coverage, data, listing_ir, html,
+    }),
+    None => {
+        eprintln!("Please provide an input file.");
+        None
+    }
+    }
+}
+
+// what 'execute' runs: IR text, parsed there, or a program loaded from a bytecode file.
+enum Runnable<'a> {
+    Ir(&'a str, Option<codegen::SourceMap>),
+    Bytecode(interpreter::Program),
+}
+
+// runs the program with stdin/stdout, or the files given with --input and --output,
+// and returns the exit status.
+fn execute(options: &Options, runnable: Runnable, debug: bool) -> i32 {
+    let mut stdin = vm_io::StdinInput;
+    let mut stdout = vm_io::StdoutOutput;
+    let mut file_input;
+    let mut file_output;
+
+    let input: &mut dyn vm_io::InputSource = match &options.input {
+    None => &mut stdin,
+    Some(path) => match vm_io::FileInput::open(path) {
+        Ok(file) => {
+            file_input = file;
+            &mut file_input
+        }
+        Err(error) => {
+            eprintln!("**Error. File \"{}\": {}", path, error);
+            return EXIT_IO_ERROR;
+        }
+    },
+    };
+
+    let output: &mut dyn vm_io::OutputSink = match &options.output {
+    None => &mut stdout,
+    Some(path) => match vm_io::FileOutput::create(path) {
+        Ok(file) => {
+            file_output = file;
+            &mut file_output
+        }
+        Err(error) => {
+            eprintln!("**Error. File \"{}\": {}", path, error);
+            return EXIT_IO_ERROR;
+        }
+    },
+    };
+
+    let mut io = vm_io::VmIo { input, output, reporter: &mut vm_io::StderrReporter };
+    let mut host = host::HostRegistry::with_builtins();
+    let mut commands = vm_io::StdinInput;
+    let mut stdout = std::io::stdout();
+    // the debugger and coverage work on the IR text, they are never used with a bytecode file.
+    let ir = match &runnable {
+    Runnable::Ir(ir, _) => *ir,
+    Runnable::Bytecode(_) => "",
+    };
+    let mut debugger = debugger::Debugger::new(ir, &mut commands, &mut stdout);
+
+    let mut stderr = std::io::stderr();
+    let mut trace_file;
+    let trace_out: &mut dyn std::io::Write = match &options.trace {
+    Some(Some(path)) => match fs::File::create(path) {
+        Ok(file) => {
+            trace_file = std::io::BufWriter::new(file);
+            &mut trace_file
+        }
+        Err(error) => {
+            eprintln!("**Error. File \"{}\": {}", path, error);
+            return EXIT_IO_ERROR;
+        }
+    },
+    _ => &mut stderr,
+    };
+    let mut tracer = trace::Tracer::new(trace_out, options.trace_functions.clone(), options.trace_limit);
+    let mut profiler = profile::Profiler::new();
+    let mut coverage = coverage::Coverage::new(ir);
+
+    let mut hooks: Vec<&mut dyn interpreter::VmHook> = vec![];
+    if debug {
+        hooks.push(&mut debugger);
+    }
+    if options.trace.is_some() {
+        hooks.push(&mut tracer);
+    }
+    if options.profile.is_some() || options.profile_folded.is_some() {
+        hooks.push(&mut profiler);
+    }
+    if options.coverage.is_some() {
+        hooks.push(&mut coverage);
+    }
+    let mut hooks = interpreter::Hooks(hooks);
+    let hook: Option<&mut dyn interpreter::VmHook> = if hooks.0.is_empty() { None } else { Some(&mut hooks) };
+    let result = match runnable {
+    Runnable::Ir(ir, source_map) => interpreter::execute_ir(ir, &options.vm, source_map, &mut io, &mut host, hook),
+    Runnable::Bytecode(program) => interpreter::execute_program(&program, &options.vm, &mut io, &mut host, hook).map_err(interpreter::ExecuteError::Runtime),
+    };
+
+    if options.trace.is_some() {
+        if let Err(error) = tracer.finish() {
+            eprintln!("**Error. Writing the trace failed: {}", error);
+            return EXIT_IO_ERROR;
+        }
+    }
+    // the profile covers the instructions up to a runtime error too.
+    let mut reports = vec![];
+    if let Some(destination) = &options.profile {
+        reports.push((destination.as_ref(), profiler.report()));
+    }
+    if let Some(path) = &options.profile_folded {
+        reports.push((Some(path), profiler.folded()));
+    }
+    // an IR error means nothing ran, leave the data as it was.
+    if let (Some(path), false) = (&options.coverage, matches!(result, Err(interpreter::ExecuteError::Ir(_)))) {
+        if let Err(error) = save_coverage(path, &mut coverage) {
+            eprintln!("**Error. File \"{}\": {}", path, error);
+            return EXIT_IO_ERROR;
+        }
+    }
+    for (destination, report) in reports {
+        match destination {
+        None => eprint!("{report}"),
+        Some(path) => {
+            if let Err(error) = fs::write(path, report) {
+                eprintln!("**Error. File \"{}\": {}", path, error);
+                return EXIT_IO_ERROR;
+            }
+        }
+        }
+    }
+
+    match result {
+    Ok(_) if options.exit_zero => 0,
+    // the os only keeps the low 8 bits, do the same on every platform.
+    Ok(value) => value & 0xff,
+    Err(interpreter::ExecuteError::Ir(_)) => EXIT_IR_ERROR,
+    Err(interpreter::ExecuteError::Runtime(e)) => match e.kind {
+        interpreter::RuntimeErrorKind::InputFailed(_) | interpreter::RuntimeErrorKind::OutputFailed(_) => EXIT_IO_ERROR,
+        _ => EXIT_RUNTIME_ERROR,
+    },
+    }
+}
+
+// adds the counts already in the data file, if there is one, and writes the total back.
+fn save_coverage(path: &str, coverage: &mut coverage::Coverage) -> Result<(), String> {
+    match fs::read_to_string(path) {
+    Ok(text) => coverage.merge(&coverage::Coverage::from_data(&text)?)?,
+    Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+    Err(error) => return Err(error.to_string()),
+    }
+    return fs::write(path, coverage.to_data()).map_err(|e| e.to_string());
+}
+
+// prints 'text', or writes it to the file given with -o.
+fn write_output(output: &Option<String>, text: &str) -> i32 {
+    match output {
+    None => print!("{text}"),
+    Some(path) => {
+        if let Err(error) = fs::write(path, text) {
+            eprintln!("**Error. File \"{}\": {}", path, error);
+            return EXIT_IO_ERROR;
+        }
+    }
+    }
+    return 0;
+}
+
+// each stage prints its own errors and returns None when it fails,
+// so every command can stop right after the stage it needs.
+
+fn report<T>(filename: &str, code: &str, result: Result<T, Vec<diagnostics::Diagnostic>>) -> Option<T> {
+    match result {
+    Ok(value) => Some(value),
+    Err(errors) => {
+        eprint!("{}", diagnostics::render_all(filename, code, &errors));
+        None
+    }
+    }
+}
+
+fn lex(filename: &str, code: &str) -> Option<Vec<lexer::SpannedToken>> {
+    report(filename, code, lexer::lex(code))
+}
+
+fn parse(filename: &str, code: &str) -> Option<ast::Program> {
+    let tokens = lex(filename, code)?;
+    report(filename, code, parser::parse_program(&tokens))
+}
+
+fn compile(filename: &str, code: &str) -> Option<(String, codegen::SourceMap)> {
+    report(filename, code, rustcompiler::compile_to_ir(code, &host::HostRegistry::with_builtins()))
 }
@@ -0,0 +1,599 @@
+use crate::ast::*;
+use crate::diagnostics::Diagnostic;
+use crate::lexer::{Span, SpannedToken, Token};
+
+// recursive descent parser for the .tt source language.
+//
+// grammar:
+//   program    := function*
+//   function   := 'func' IDENT '(' (param (',' param)*)? ')' block
+//   param      := 'int' IDENT
+//   block      := '{' stmt* '}'
+//   stmt       := 'int' IDENT ('=' expr)? ';'
+//               | 'int' '[' NUM ']' IDENT ';'
+//               | place '=' expr ';'
+//               | 'while' expr block
+//               | 'if' expr block ('else' (block | if-stmt))?
+//               | 'break' ';' | 'continue' ';'
+//               | 'print' '(' expr ')' ';'
+//               | 'read' '(' place ')' ';'
+//               | 'return' expr ';'
+//               | call ';'
+//   place      := IDENT ('[' expr ']')?
+//
+// expression precedence, from loosest to tightest binding.
+// every binary operator is left associative.
+//
+//   level | operators
+//   ------+------------------
+//     1   | ==  !=
+//     2   | <  <=  >  >=
+//     3   | +  -
+//     4   | *  /  %
+//     5   | unary -
+//     6   | literals, variables, a[i], f(x, y), ( expr )
+//
+// the later passes walk the AST recursively, so nesting is limited to MAX_NESTING levels
+// and reported as E0205 instead of overflowing the stack. blocks count their enclosing
+// blocks (an 'else if' sits in the else block of the 'if' before it). expressions count
+// the blocks around them, parentheses, unary minus, array indexes, call arguments and
+// the operators in a chain like '1 + 2 + 3', which nests to the left.
+//
+// error recovery: a broken statement is skipped up to the next ';', '}' or statement
+// keyword, a broken function header up to the next 'func'. parsing then continues so
+// that every syntax error in the file is reported in one run.
+
+const MAX_NESTING: usize = 128;
+
+fn error<T>(code: &'static str, span: Span, message: String) -> Result<T, Diagnostic> {
+    Err(Diagnostic::new(code, span, message))
+}
+
+// tokens must end with Token::End, which is what lexer::lex produces.
+pub fn parse_program(tokens: &[SpannedToken]) -> Result<Program, Vec<Diagnostic>> {
+    let mut idx: usize = 0;
+    let mut functions: Vec<Function> = vec![];
+    let mut errors: Vec<Diagnostic> = vec![];
+    while !matches!(peek(tokens, idx).token, Token::End) {
+        let start = idx;
+        match parse_function(tokens, &mut idx, &mut errors) {
+        Ok(function) => functions.push(function),
+        Err(e) => {
+            errors.push(e);
+            if idx == start {
+                idx += 1;
+            }
+            while !matches!(peek(tokens, idx).token, Token::Func | Token::End) {
+                idx += 1;
+            }
+        }
+        }
+    }
+
+    if errors.is_empty() {
+        return Ok(Program { functions });
+    }
+    return Err(errors);
+}
+
+fn parse_function(tokens: &[SpannedToken], idx: &mut usize, errors: &mut Vec<Diagnostic>) -> Result<Function, Diagnostic> {
+    let start = expect(tokens, idx, Token::Func, "functions must begin with 'func'")?;
+    let name = expect_ident(tokens, idx, "expected a function name such as 'func main'")?;
+    expect(tokens, idx, Token::LeftParen, "expected '(' after the function name")?;
+
+    let mut params: Vec<Param> = vec![];
+    if !matches!(peek(tokens, *idx).token, Token::RightParen) {
+        loop {
+            let int = expect(tokens, idx, Token::Int, "expected a parameter such as 'int a'")?;
+            let name = expect_ident(tokens, idx, "expected a parameter name such as 'int a'")?;
+            let span = int.to(name.span);
+            params.push(Param { name, span });
+            if !eat(tokens, idx, Token::Comma) {
+                break;
+            }
+        }
+    }
+    expect(tokens, idx, Token::RightParen, "expected ')' after the parameter list")?;
+
+    let body = parse_block(tokens, idx, errors, 0)?;
+    let span = start.to(body.span);
+    return Ok(Function { name, params, body, span });
+}
+
+// 'depth' is the number of blocks around this one.
+fn parse_block(tokens: &[SpannedToken], idx: &mut usize, errors: &mut Vec<Diagnostic>, depth: usize) -> Result<Block, Diagnostic> {
+    let start = expect(tokens, idx, Token::LeftCurly, "expected '{'")?;
+    if depth > MAX_NESTING {
+        skip_nested(tokens, idx);
+        return error("E0205", start, format!("block is nested more than {MAX_NESTING} levels deep"));
+    }
+    let mut stmts: Vec<Stmt> = vec![];
+    loop {
+        match peek(tokens, *idx).token {
+        Token::RightCurly => break,
+        Token::End => {
+            let message = String::from("missing '}'. reached the end of the file inside a block");
+            let e = Diagnostic::new("E0202", peek(tokens, *idx).span, message)
+                .with_note(Some(start), "the block starts here");
+            return Err(e);
+        }
+        _ => {
+            let before = *idx;
+            match parse_statement(tokens, idx, errors, depth) {
+            Ok(stmt) => stmts.push(stmt),
+            Err(e) => {
+                errors.push(e);
+                synchronize(tokens, idx, before);
+            }
+            }
+        }
+        }
+    }
+    let end = next(tokens, idx).span;
+    return Ok(Block { stmts, span: start.to(end) });
+}
+
+// skips a block that is nested too deeply, from after its '{', together with the 'else'
+// and 'else if' blocks following it, so the whole construct is reported once.
+fn skip_nested(tokens: &[SpannedToken], idx: &mut usize) {
+    let mut open = 1;
+    loop {
+        match next(tokens, idx).token {
+        Token::LeftCurly => open += 1,
+        Token::RightCurly if open == 1 => {
+            if !eat(tokens, idx, Token::Else) {
+                return;
+            }
+            // the condition of an 'else if' has no braces, go into the next block.
+            while !matches!(peek(tokens, *idx).token, Token::LeftCurly | Token::End) {
+                *idx += 1;
+            }
+            eat(tokens, idx, Token::LeftCurly);
+        }
+        Token::RightCurly => open -= 1,
+        Token::End => return,
+        _ => {}
+        }
+    }
+}
+
+// skips the rest of a broken statement.
+fn synchronize(tokens: &[SpannedToken], idx: &mut usize, before: usize) {
+    if *idx == before {
+        *idx += 1;
+    }
+    loop {
+        match peek(tokens, *idx).token {
+        Token::Semicolon => {
+            *idx += 1;
+            return;
+        }
+        Token::RightCurly | Token::End | Token::Func => return,
+        Token::Int | Token::While | Token::If | Token::Break | Token::Continue
+        | Token::Print | Token::Read | Token::Return => return,
+        _ => *idx += 1,
+        }
+    }
+}
+
+fn parse_statement(tokens: &[SpannedToken], idx: &mut usize, errors: &mut Vec<Diagnostic>, depth: usize) -> Result<Stmt, Diagnostic> {
+    let start = peek(tokens, *idx).span;
+    let kind = match peek(tokens, *idx).token {
+
+    Token::Int => {
+        *idx += 1;
+        let ty = if eat(tokens, idx, Token::LeftBracket) {
+            let size = match &next(tokens, idx).token {
+            Token::Num(num) => *num,
+            _ => return error("E0201", tokens[*idx - 1].span, String::from("array size must be a number, e.g. 'int[100] a;'")),
+            };
+            expect(tokens, idx, Token::RightBracket, "expected ']' after the array size")?;
+            VarType::Array(size)
+        } else {
+            VarType::Int
+        };
+
+        let name = expect_ident(tokens, idx, "expected a variable name in the declaration")?;
+        let init = if eat(tokens, idx, Token::Assign) {
+            if let VarType::Array(_) = ty {
+                return error("E0201", tokens[*idx - 1].span, String::from("arrays cannot be initialized in their declaration"));
+            }
+            Some(parse_binary(tokens, idx, 1, depth)?)
+        } else {
+            None
+        };
+        expect_semicolon(tokens, idx, errors);
+        StmtKind::Declare { ty, name, init }
+    }
+
+    Token::While => {
+        *idx += 1;
+        let cond = parse_binary(tokens, idx, 1, depth)?;
+        let body = parse_block(tokens, idx, errors, depth + 1)?;
+        StmtKind::While { cond, body }
+    }
+
+    Token::If => {
+        return parse_if(tokens, idx, errors, depth);
+    }
+
+    Token::Break => {
+        *idx += 1;
+        expect_semicolon(tokens, idx, errors);
+        StmtKind::Break
+    }
+
+    Token::Continue => {
+        *idx += 1;
+        expect_semicolon(tokens, idx, errors);
+        StmtKind::Continue
+    }
+
+    Token::Print => {
+        *idx += 1;
+        expect(tokens, idx, Token::LeftParen, "expected '(' after 'print'")?;
+        let expr = parse_binary(tokens, idx, 1, depth)?;
+        expect(tokens, idx, Token::RightParen, "expected ')' after the 'print' argument")?;
+        expect_semicolon(tokens, idx, errors);
+        StmtKind::Print(expr)
+    }
+
+    Token::Read => {
+        *idx += 1;
+        expect(tokens, idx, Token::LeftParen, "expected '(' after 'read'")?;
+        let expr = parse_binary(tokens, idx, 1, depth)?;
+        let place = match into_place(expr) {
+        Some(place) => place,
+        None => return error("E0203", tokens[*idx - 1].span, String::from("'read' needs a variable or an array element, e.g. 'read(a);'")),
+        };
+        expect(tokens, idx, Token::RightParen, "expected ')' after the 'read' argument")?;
+        expect_semicolon(tokens, idx, errors);
+        StmtKind::Read(place)
+    }
+
+    Token::Return => {
+        *idx += 1;
+        let expr = parse_binary(tokens, idx, 1, depth)?;
+        expect_semicolon(tokens, idx, errors);
+        StmtKind::Return(expr)
+    }
+
+    Token::Ident(_) => {
+        let expr = parse_binary(tokens, idx, 1, depth)?;
+        if matches!(peek(tokens, *idx).token, Token::Assign) {
+            let assign = next(tokens, idx).span;
+            let target = match into_place(expr) {
+            Some(place) => place,
+            None => return error("E0203", assign, String::from("the left side of '=' must be a variable or an array element")),
+            };
+            let value = parse_binary(tokens, idx, 1, depth)?;
+            expect_semicolon(tokens, idx, errors);
+            StmtKind::Assign { target, value }
+        } else if matches!(expr.kind, ExprKind::Call { .. }) {
+            expect_semicolon(tokens, idx, errors);
+            StmtKind::Expr(expr)
+        } else {
+            let t = peek(tokens, *idx);
+            return error("E0204", t.span, format!("expected '=' or ';' but found {}. only assignments and calls can be used as statements", t.token));
+        }
+    }
+
+    _ => {
+        let t = peek(tokens, *idx);
+        return error("E0204", t.span, format!("expected a statement but found {}", t.token));
+    }
+
+    };
+
+    let end = tokens[*idx - 1].span;
+    return Ok(Stmt { kind, span: start.to(end) });
+}
+
+fn parse_if(tokens: &[SpannedToken], idx: &mut usize, errors: &mut Vec<Diagnostic>, depth: usize) -> Result<Stmt, Diagnostic> {
+    let start = expect(tokens, idx, Token::If, "expected 'if'")?;
+    let cond = parse_binary(tokens, idx, 1, depth)?;
+    let then_block = parse_block(tokens, idx, errors, depth + 1)?;
+    let else_block = if eat(tokens, idx, Token::Else) {
+        if matches!(peek(tokens, *idx).token, Token::If) {
+            // 'else if' is sugar for an else block holding a single if statement.
+            let stmt = parse_if(tokens, idx, errors, depth + 1)?;
+            let span = stmt.span;
+            Some(Block { stmts: vec![stmt], span })
+        } else {
+            Some(parse_block(tokens, idx, errors, depth + 1)?)
+        }
+    } else {
+        None
+    };
+    let end = tokens[*idx - 1].span;
+    let kind = StmtKind::If { cond, then_block, else_block };
+    return Ok(Stmt { kind, span: start.to(end) });
+}
+
+fn into_place(expr: Expr) -> Option<Place> {
+    match expr.kind {
+    ExprKind::Var(name) => {
+        let name = Ident { name, span: expr.span };
+        Some(Place { name, index: None, span: expr.span })
+    }
+    ExprKind::Index { array, index } => {
+        Some(Place { name: array, index: Some(*index), span: expr.span })
+    }
+    _ => None,
+    }
+}
+
+// binding power of each binary operator, see the precedence table at the top of the file.
+fn binary_op(token: &Token) -> Option<(BinaryOp, u8)> {
+    match token {
+    Token::Equality => Some((BinaryOp::Equal, 1)),
+    Token::NotEqual => Some((BinaryOp::NotEqual, 1)),
+    Token::Less => Some((BinaryOp::Less, 2)),
+    Token::LessEqual => Some((BinaryOp::LessEqual, 2)),
+    Token::Greater => Some((BinaryOp::Greater, 2)),
+    Token::GreaterEqual => Some((BinaryOp::GreaterEqual, 2)),
+    Token::Plus => Some((BinaryOp::Add, 3)),
+    Token::Subtract => Some((BinaryOp::Sub, 3)),
+    Token::Multiply => Some((BinaryOp::Mul, 4)),
+    Token::Divide => Some((BinaryOp::Div, 4)),
+    Token::Modulus => Some((BinaryOp::Mod, 4)),
+    _ => None,
+    }
+}
+
+pub fn parse_expression(tokens: &[SpannedToken], idx: &mut usize) -> Result<Expr, Diagnostic> {
+    return parse_binary(tokens, idx, 1, 0);
+}
+
+// 'depth' is the number of enclosing parentheses, unary minus, indexes and calls.
+fn parse_binary(tokens: &[SpannedToken], idx: &mut usize, min_power: u8, depth: usize) -> Result<Expr, Diagnostic> {
+    let mut lhs = parse_unary(tokens, idx, depth)?;
+    let mut chain = 0;
+    while let Some((op, power)) = binary_op(&peek(tokens, *idx).token) {
+        if power < min_power {
+            break;
+        }
+        chain += 1;
+        if depth + chain > MAX_NESTING {
+            return error("E0205", peek(tokens, *idx).span, format!("expression has more than {MAX_NESTING} operators in a row, split it over several statements"));
+        }
+        *idx += 1;
+        let rhs = parse_binary(tokens, idx, power + 1, depth + chain)?;
+        let span = lhs.span.to(rhs.span);
+        lhs = Expr { kind: ExprKind::Binary { op, lhs: Box::new(lhs), rhs: Box::new(rhs) }, span };
+    }
+    return Ok(lhs);
+}
+
+fn parse_unary(tokens: &[SpannedToken], idx: &mut usize, depth: usize) -> Result<Expr, Diagnostic> {
+    if depth > MAX_NESTING {
+        return error("E0205", peek(tokens, *idx).span, format!("expression is nested more than {MAX_NESTING} levels deep"));
+    }
+    if matches!(peek(tokens, *idx).token, Token::Subtract) {
+        let start = next(tokens, idx).span;
+        let expr = parse_unary(tokens, idx, depth + 1)?;
+        let span = start.to(expr.span);
+        return Ok(Expr { kind: ExprKind::Unary { op: UnaryOp::Neg, expr: Box::new(expr) }, span });
+    }
+    return parse_primary(tokens, idx, depth);
+}
+
+fn parse_primary(tokens: &[SpannedToken], idx: &mut usize, depth: usize) -> Result<Expr, Diagnostic> {
+    let t = next(tokens, idx);
+    match &t.token {
+    Token::Num(num) => {
+        return Ok(Expr { kind: ExprKind::Num(*num), span: t.span });
+    }
+
+    Token::LeftParen => {
+        let mut expr = parse_binary(tokens, idx, 1, depth + 1)?;
+        let end = expect(tokens, idx, Token::RightParen, "expected ')' to close the parenthesized expression")?;
+        expr.span = t.span.to(end);
+        return Ok(expr);
+    }
+
+    Token::Ident(name) => {
+        let ident = Ident { name: name.clone(), span: t.span };
+        if eat(tokens, idx, Token::LeftBracket) {
+            let index = parse_binary(tokens, idx, 1, depth + 1)?;
+            let end = expect(tokens, idx, Token::RightBracket, "expected ']' after the array index")?;
+            let kind = ExprKind::Index { array: ident, index: Box::new(index) };
+            return Ok(Expr { kind, span: t.span.to(end) });
+        }
+
+        if eat(tokens, idx, Token::LeftParen) {
+            let mut args: Vec<Expr> = vec![];
+            if !matches!(peek(tokens, *idx).token, Token::RightParen) {
+                loop {
+                    args.push(parse_binary(tokens, idx, 1, depth + 1)?);
+                    if !eat(tokens, idx, Token::Comma) {
+                        break;
+                    }
+                }
+            }
+            let end = expect(tokens, idx, Token::RightParen, "expected ')' after the call arguments")?;
+            let kind = ExprKind::Call { name: ident, args };
+            return Ok(Expr { kind, span: t.span.to(end) });
+        }
+
+        return Ok(Expr { kind: ExprKind::Var(name.clone()), span: t.span });
+    }
+
+    _ => {
+        return error("E0201", t.span, format!("expected an expression but found {}", t.token));
+    }
+
+    }
+}
+
+fn peek(tokens: &[SpannedToken], idx: usize) -> &SpannedToken {
+    // the last token is always Token::End, so never read past it.
+    let last = tokens.len() - 1;
+    &tokens[idx.min(last)]
+}
+
+fn next<'a>(tokens: &'a [SpannedToken], idx: &mut usize) -> &'a SpannedToken {
+    let t = peek(tokens, *idx);
+    if *idx < tokens.len() - 1 {
+        *idx += 1;
+    }
+    t
+}
+
+fn eat(tokens: &[SpannedToken], idx: &mut usize, expected: Token) -> bool {
+    if peek(tokens, *idx).token == expected {
+        *idx += 1;
+        return true;
+    }
+    return false;
+}
+
+fn expect(tokens: &[SpannedToken], idx: &mut usize, expected: Token, message: &str) -> Result<Span, Diagnostic> {
+    let t = peek(tokens, *idx);
+    if t.token == expected {
+        *idx += 1;
+        return Ok(t.span);
+    }
+    return error("E0201", t.span, format!("{message}, found {}", t.token));
+}
+
+fn expect_ident(tokens: &[SpannedToken], idx: &mut usize, message: &str) -> Result<Ident, Diagnostic> {
+    let t = peek(tokens, *idx);
+    if let Token::Ident(name) = &t.token {
+        *idx += 1;
+        return Ok(Ident { name: name.clone(), span: t.span });
+    }
+    return error("E0201", t.span, format!("{message}, found {}", t.token));
+}
+
+// a missing ';' is reported but does not abort the statement, the parser carries on
+// as if it was there. this keeps one forgotten ';' from hiding the next statement.
+fn expect_semicolon(tokens: &[SpannedToken], idx: &mut usize, errors: &mut Vec<Diagnostic>) {
+    let t = peek(tokens, *idx);
+    if t.token == Token::Semicolon {
+        *idx += 1;
+        return;
+    }
+    // point right after the previous token, which is where the ';' belongs.
+    let prev = tokens[idx.saturating_sub(1)].span;
+    let span = Span { start: prev.end, end: prev.end, line: prev.line, col: prev.col + (prev.end - prev.start) };
+    errors.push(Diagnostic::new("E0201", span, format!("expected ';' but found {}", t.token)));
+}
+
+#[cfg(test)]
+mod parser_tests {
+    use crate::lexer::lex;
+    use crate::parser::*;
+
+    fn parse(code: &str) -> Result<Program, Vec<Diagnostic>> {
+        parse_program(&lex(code).ok().unwrap())
+    }
+
+    fn parse_expr(code: &str) -> Expr {
+        let tokens = lex(code).ok().unwrap();
+        parse_expression(&tokens, &mut 0).ok().unwrap()
+    }
+
+    #[test]
+    fn parse_precedence() {
+        // 1 + 2 * 3 < 4 == 1 parses as ((1 + (2 * 3)) < 4) == 1
+        let expr = parse_expr("1 + 2 * 3 < 4 == 1");
+        let ExprKind::Binary { op: BinaryOp::Equal, lhs, .. } = expr.kind else { panic!() };
+        let ExprKind::Binary { op: BinaryOp::Less, lhs, .. } = lhs.kind else { panic!() };
+        let ExprKind::Binary { op: BinaryOp::Add, rhs, .. } = lhs.kind else { panic!() };
+        assert!(matches!(rhs.kind, ExprKind::Binary { op: BinaryOp::Mul, .. }));
+
+        // left associative: 10 - 2 - 3 parses as (10 - 2) - 3
+        let expr = parse_expr("10 - 2 - 3");
+        let ExprKind::Binary { op: BinaryOp::Sub, lhs, rhs } = expr.kind else { panic!() };
+        assert!(matches!(lhs.kind, ExprKind::Binary { op: BinaryOp::Sub, .. }));
+        assert!(matches!(rhs.kind, ExprKind::Num(3)));
+
+        let expr = parse_expr("-a[i] * f(1, 2)");
+        assert!(expr.span.start == 0 && expr.span.end == 15);
+        let ExprKind::Binary { op: BinaryOp::Mul, lhs, rhs } = expr.kind else { panic!() };
+        assert!(matches!(lhs.kind, ExprKind::Unary { op: UnaryOp::Neg, .. }));
+        assert!(matches!(rhs.kind, ExprKind::Call { ref args, .. } if args.len() == 2));
+    }
+
+    #[test]
+    fn parse_statements() {
+        let program = parse("func add(int a, int b) { return a + b; }\nfunc main() {\n int[10] arr;\n int i = 0;\n arr[i] = add(i, 2);\n if i < 1 { break; } else if i < 2 { continue; } else { print(i); }\n read(arr[1]);\n add(1, 2);\n}").ok().unwrap();
+        assert!(program.functions.len() == 2);
+        assert!(program.functions[0].params.len() == 2);
+
+        let body = &program.functions[1].body.stmts;
+        assert!(body.len() == 6);
+        assert!(matches!(body[0].kind, StmtKind::Declare { ty: VarType::Array(10), init: None, .. }));
+        assert!(matches!(body[1].kind, StmtKind::Declare { ty: VarType::Int, init: Some(_), .. }));
+        assert!(matches!(body[2].kind, StmtKind::Assign { target: Place { index: Some(_), .. }, .. }));
+        assert!(matches!(body[3].kind, StmtKind::If { else_block: Some(_), .. }));
+        assert!(matches!(body[4].kind, StmtKind::Read(_)));
+        assert!(matches!(body[5].kind, StmtKind::Expr(_)));
+        assert!(body[2].span.line == 5 && body[2].span.col == 2);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let e = parse("func main() {\n    int a\n}").err().unwrap();
+        assert!(e.len() == 1);
+        assert!(e[0].span.line == 2 && e[0].span.col == 10);
+
+        let e = parse("func main() { 1 + 2; }").err().unwrap();
+        assert!(e[0].code == "E0204" && e[0].message.contains("expected a statement"));
+
+        let e = parse("func main() { a + 2; }").err().unwrap();
+        assert!(e[0].message.contains("only assignments and calls"));
+
+        let e = parse("func main() { while i < 10 { }").err().unwrap();
+        assert!(e.len() == 1 && e[0].code == "E0202");
+    }
+
+    #[test]
+    fn parse_recovery() {
+        // every broken statement is reported, and parsing continues in the next function.
+        let e = parse("func main() {\n    int a\n    a = ;\n    print(a);\n    a + 1 = a;\n}\nfunc (int a) {}\nfunc f() { int; }").err().unwrap();
+        assert!(e.len() == 5);
+        assert!(e[0].span.line == 2);
+        assert!(e[1].span.line == 3 && e[1].message.contains("expected an expression"));
+        assert!(e[2].span.line == 5 && e[2].code == "E0203");
+        assert!(e[3].span.line == 7);
+        assert!(e[4].span.line == 8);
+    }
+
+    #[test]
+    fn parse_nesting() {
+        let nested = |depth: usize| format!("func main() {{ print({}1{}); }}", "(".repeat(depth), ")".repeat(depth));
+        assert!(crate::compile_to_ir(&nested(MAX_NESTING), &crate::host::HostRegistry::new()).is_ok());
+        let e = parse(&nested(MAX_NESTING + 1)).err().unwrap();
+        assert!(e.len() == 1 && e[0].code == "E0205");
+
+        // far too deep to recurse into, reported instead of overflowing the stack.
+        let e = parse(&format!("func main() {{ print({}1); }}", "-".repeat(100000))).err().unwrap();
+        assert!(e[0].code == "E0205" && e[0].span.col == 21 + MAX_NESTING + 1);
+    }
+
+    #[test]
+    fn parse_nested_blocks() {
+        let host = crate::host::HostRegistry::new();
+        let whiles = |depth: usize, inner: &str| format!("func main() {{ {}{inner}{} }}", "while 1 { ".repeat(depth), "} ".repeat(depth));
+        let deepest = format!("print({}1{});", "(".repeat(MAX_NESTING / 2), ")".repeat(MAX_NESTING / 2));
+        assert!(crate::compile_to_ir(&whiles(MAX_NESTING / 2, &deepest), &host).is_ok());
+        let e = parse(&whiles(5000, "break;")).err().unwrap();
+        assert!(e.len() == 1 && e[0].code == "E0205" && e[0].span.col == 15 + 10 * MAX_NESTING + 8);
+
+        let chain = |len: usize| format!("func main() {{ if 0 {{ }}{} else {{ print(1); }} print(2); }}", " else if 0 { }".repeat(len));
+        assert!(crate::compile_to_ir(&chain(MAX_NESTING - 1), &host).is_ok());
+        let e = parse(&chain(20000)).err().unwrap();
+        assert!(e.len() == 1 && e[0].code == "E0205");
+    }
+
+    #[test]
+    fn parse_operator_chain() {
+        let host = crate::host::HostRegistry::new();
+        let sum = |terms: usize| format!("func main() {{ print(1{}); print(2); }}", " + 1".repeat(terms - 1));
+        assert!(crate::compile_to_ir(&sum(MAX_NESTING + 1), &host).is_ok());
+        let e = parse(&sum(20000)).err().unwrap();
+        assert!(e.len() == 1 && e[0].code == "E0205" && e[0].message.contains("operators in a row"));
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::interpreter::{HookAction, VmHook, VmState};
+
+// counts executed instructions per opcode, function, label region and call edge.
+//
+// a function's exclusive count is the instructions executed in its own body, the
+// inclusive count adds everything its calls executed. recursive calls are only
+// counted once towards the inclusive count. a label region runs from a label to the
+// next one, the instructions before the first label are the '(entry)' region.
+
+struct FunctionProfile {
+    name: String,
+    calls: u64,
+    exclusive: u64,
+    inclusive: u64,
+
+    // number of activations on the stack right now.
+    active: usize,
+
+    // region names with their counts, and the region of every instruction.
+    regions: Vec<(String, u64)>,
+    region_of: Vec<usize>,
+}
+
+// one distinct call stack, for the folded output.
+struct StackNode {
+    parent: Option<usize>,
+    function: usize,
+    count: u64,
+}
+
+struct Activation {
+    node: usize,
+    function: usize,
+    // the total when the function was entered.
+    entry: u64,
+}
+
+// what the instruction being executed does to the call stack.
+enum Pending {
+    None,
+    Call,
+    Return,
+}
+
+pub struct Profiler {
+    total: u64,
+    opcodes: HashMap<&'static str, u64>,
+    functions: Vec<FunctionProfile>,
+    ids: HashMap<String, usize>,
+    edges: HashMap<(usize, usize), u64>,
+    nodes: Vec<StackNode>,
+    children: HashMap<(Option<usize>, usize), usize>,
+    stack: Vec<Activation>,
+    pending: Pending,
+}
+
+impl Default for Profiler {
+    fn default() -> Profiler {
+        Profiler::new()
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler {
+            total: 0,
+            opcodes: HashMap::new(),
+            functions: vec![],
+            ids: HashMap::new(),
+            edges: HashMap::new(),
+            nodes: vec![],
+            children: HashMap::new(),
+            stack: vec![],
+            pending: Pending::None,
+        }
+    }
+
+    fn function_id(&mut self, state: &VmState) -> usize {
+        let function = state.function();
+        if let Some(id) = self.ids.get(function.name()) {
+            return *id;
+        }
+
+        let mut regions = vec![(String::from("(entry)"), 0)];
+        let mut region_of = vec![];
+        for index in 0..=function.len() {
+            if let Some(label) = function.label_name(index) {
+                regions.push((String::from(label), 0));
+            }
+            region_of.push(regions.len() - 1);
+        }
+        let name = String::from(function.name());
+        self.functions.push(FunctionProfile { name: name.clone(), calls: 0, exclusive: 0, inclusive: 0, active: 0, regions, region_of });
+        self.ids.insert(name, self.functions.len() - 1);
+        return self.functions.len() - 1;
+    }
+
+    fn enter(&mut self, state: &VmState) {
+        let function = self.function_id(state);
+        let parent = self.stack.last().map(|caller| caller.node);
+        if let Some(caller) = self.stack.last() {
+            *self.edges.entry((caller.function, function)).or_insert(0) += 1;
+        }
+        let node = match self.children.get(&(parent, function)) {
+        Some(node) => *node,
+        None => {
+            self.nodes.push(StackNode { parent, function, count: 0 });
+            self.children.insert((parent, function), self.nodes.len() - 1);
+            self.nodes.len() - 1
+        }
+        };
+
+        let profile = &mut self.functions[function];
+        profile.calls += 1;
+        profile.active += 1;
+        self.stack.push(Activation { node, function, entry: self.total });
+    }
+
+    fn leave(&mut self) {
+        let Some(activation) = self.stack.pop() else { return };
+        let profile = &mut self.functions[activation.function];
+        profile.active -= 1;
+        if profile.active == 0 {
+            profile.inclusive += self.total - activation.entry;
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    // inclusive counts, with the functions still running when the run ended (on an error)
+    // counted up to the last instruction.
+    fn inclusive(&self) -> Vec<u64> {
+        let mut inclusive: Vec<u64> = self.functions.iter().map(|f| f.inclusive).collect();
+        let mut counted = vec![false; self.functions.len()];
+        for activation in &self.stack {
+            if !counted[activation.function] {
+                counted[activation.function] = true;
+                inclusive[activation.function] += self.total - activation.entry;
+            }
+        }
+        return inclusive;
+    }
+
+    // the human-readable report.
+    pub fn report(&self) -> String {
+        let percent = |count: u64| if self.total == 0 { 0.0 } else { count as f64 * 100.0 / self.total as f64 };
+        let mut out = String::new();
+        let _ = writeln!(out, "{} instructions executed.", self.total);
+
+        let inclusive = self.inclusive();
+        let mut functions: Vec<usize> = (0..self.functions.len()).collect();
+        functions.sort_by(|a, b| self.functions[*b].exclusive.cmp(&self.functions[*a].exclusive).then(self.functions[*a].name.cmp(&self.functions[*b].name)));
+        let width = self.functions.iter().map(|f| f.name.len()).max().unwrap_or(0).max("function".len());
+        let _ = writeln!(out, "\n{:width$}  {:>10}  {:>12}  {:>6}  {:>12}  {:>6}", "function", "calls", "exclusive", "%", "inclusive", "%");
+        for id in functions {
+            let f = &self.functions[id];
+            let _ = writeln!(out, "{:width$}  {:>10}  {:>12}  {:>5.1}%  {:>12}  {:>5.1}%", f.name, f.calls, f.exclusive, percent(f.exclusive), inclusive[id], percent(inclusive[id]));
+        }
+
+        let mut regions: Vec<(String, u64)> = vec![];
+        for f in &self.functions {
+            for (label, count) in f.regions.iter().filter(|(_, count)| *count > 0) {
+                regions.push((format!("{} {}", f.name, label), *count));
+            }
+        }
+        regions.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let width = regions.iter().map(|(name, _)| name.len()).max().unwrap_or(0).max("label region".len());
+        let _ = writeln!(out, "\n{:width$}  {:>12}  {:>6}", "label region", "instructions", "%");
+        for (name, count) in regions {
+            let _ = writeln!(out, "{:width$}  {:>12}  {:>5.1}%", name, count, percent(count));
+        }
+
+        let mut edges: Vec<(String, u64)> = self.edges.iter()
+            .map(|((caller, callee), count)| (format!("{} -> {}", self.functions[*caller].name, self.functions[*callee].name), *count))
+            .collect();
+        edges.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let width = edges.iter().map(|(name, _)| name.len()).max().unwrap_or(0).max("call edge".len());
+        let _ = writeln!(out, "\n{:width$}  {:>10}", "call edge", "calls");
+        for (name, count) in edges {
+            let _ = writeln!(out, "{:width$}  {:>10}", name, count);
+        }
+
+        let mut opcodes: Vec<(&str, u64)> = self.opcodes.iter().map(|(opcode, count)| (*opcode, *count)).collect();
+        opcodes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        let _ = writeln!(out, "\n{:12}  {:>12}  {:>6}", "instruction", "count", "%");
+        for (opcode, count) in opcodes {
+            let opcode = if opcode == ":" { ":label" } else { opcode };
+            let _ = writeln!(out, "{:12}  {:>12}  {:>5.1}%", opcode, count, percent(count));
+        }
+        return out;
+    }
+
+    // one line per distinct call stack, 'main;fact;fact 12', the format flamegraph tools read.
+    pub fn folded(&self) -> String {
+        // parents are always created before their children.
+        let mut paths: Vec<String> = Vec::with_capacity(self.nodes.len());
+        let mut lines: Vec<String> = vec![];
+        for node in &self.nodes {
+            let name = &self.functions[node.function].name;
+            let path = match node.parent {
+            Some(parent) => format!("{};{}", paths[parent], name),
+            None => name.clone(),
+            };
+            if node.count > 0 {
+                lines.push(format!("{} {}\n", path, node.count));
+            }
+            paths.push(path);
+        }
+        lines.sort();
+        return lines.concat();
+    }
+}
+
+impl VmHook for Profiler {
+    fn before_instruction(&mut self, state: &VmState) -> HookAction {
+        if self.stack.is_empty() {
+            self.enter(state);
+        }
+
+        self.total += 1;
+        *self.opcodes.entry(state.opcode()).or_insert(0) += 1;
+        let activation = self.stack.last().unwrap();
+        self.nodes[activation.node].count += 1;
+        let profile = &mut self.functions[activation.function];
+        profile.exclusive += 1;
+        profile.regions[profile.region_of[state.ip()]].1 += 1;
+
+        self.pending = match state.opcode() {
+        "%ret" | "%endfunc" => Pending::Return,
+        _ if state.calls_function() => Pending::Call,
+        _ => Pending::None,
+        };
+        return HookAction::Continue;
+    }
+
+    fn after_instruction(&mut self, state: &VmState, _result: Option<i32>) {
+        match self.pending {
+        Pending::Call => self.enter(state),
+        Pending::Return => self.leave(),
+        Pending::None => {}
+        }
+        self.pending = Pending::None;
+    }
+}
+
+#[cfg(test)]
+mod profile_tests {
+    use crate::host::HostRegistry;
+    use crate::interpreter::{parse_program, VmConfig};
+    use crate::profile::*;
+    use crate::vm_io::*;
+
+    #[test]
+    fn profile_counts() {
+        // fact(3) runs 3 times, the last call returns early.
+        let code = "%func fact(%int n)\n    %int c\n    %lt c, n, 2\n    %branch_ifn c, :rec\n    %ret 1\n:rec\n    %sub c, n, 1\n    %call c, fact(c)\n    %mult c, c, n\n    %ret c\n%endfunc\n%func main()\n    %int a\n    %call a, fact(3)\n    %out a\n%endfunc\n";
+        let mut host = HostRegistry::new();
+        let program = parse_program(code, &host).ok().unwrap();
+        let (mut input, mut output, mut reporter) = (MemoryInput::new(""), MemoryOutput::default(), MemoryReporter::default());
+        let mut io = VmIo { input: &mut input, output: &mut output, reporter: &mut reporter };
+        let mut profiler = Profiler::new();
+        assert!(matches!(program.run_with_hook(&VmConfig::default(), &mut io, &mut host, &mut profiler), Ok(0)));
+
+        // main: 4 instructions. fact: 2 calls of 8 instructions and one of 4.
+        assert!(profiler.total() == 24);
+        let fact = &profiler.functions[profiler.ids["fact"]];
+        assert!(fact.calls == 3 && fact.exclusive == 20 && fact.inclusive == 20);
+        assert!(fact.regions == vec![(String::from("(entry)"), 10), (String::from(":rec"), 10)]);
+        let main = &profiler.functions[profiler.ids["main"]];
+        assert!(main.calls == 1 && main.exclusive == 4 && main.inclusive == 24);
+        assert!(profiler.edges[&(profiler.ids["main"], profiler.ids["fact"])] == 1);
+        assert!(profiler.edges[&(profiler.ids["fact"], profiler.ids["fact"])] == 2);
+        assert!(profiler.opcodes["%ret"] == 3 && profiler.opcodes["%call"] == 3);
+
+        assert!(profiler.folded() == "main 4\nmain;fact 8\nmain;fact;fact 8\nmain;fact;fact;fact 4\n");
+        let report = profiler.report();
+        assert!(report.starts_with("24 instructions executed.\n\nfunction       calls     exclusive       %     inclusive       %\n"));
+        assert!(report.contains("\nfact               3            20   83.3%            20   83.3%\nmain               1             4   16.7%            24  100.0%\n"));
+        assert!(report.contains("\nfact (entry)            10   41.7%\n"));
+        assert!(report.contains("\nfact -> fact           2\n"));
+    }
+}
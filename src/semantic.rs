@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+
+use crate::ast::*;
+use crate::diagnostics::Diagnostic;
+use crate::host::HostRegistry;
+use crate::lexer::Span;
+
+// semantic analysis of the AST, run before codegen.
+// mirrors the checks the IR parser does (undeclared/duplicate identifiers, int vs array
+// operands, call arity) but reports them against the .tt source.
+// calls may also target the host functions the program will run with.
+
+struct FunctionInfo {
+    parameters: usize,
+    span: Span,
+}
+
+struct Checker<'a> {
+    functions: &'a HashMap<String, FunctionInfo>,
+    host: &'a HostRegistry,
+    scopes: Vec<HashMap<String, (VarType, Span)>>,
+    loop_depth: usize,
+    errors: Vec<Diagnostic>,
+}
+
+pub fn check_program(program: &Program, host: &HostRegistry) -> Result<(), Vec<Diagnostic>> {
+    let mut errors: Vec<Diagnostic> = vec![];
+    let mut functions: HashMap<String, FunctionInfo> = HashMap::new();
+
+    for function in &program.functions {
+        let name = &function.name;
+        if let Some(previous) = functions.get(&name.name) {
+            let message = format!("function '{}' is already declared.", name.name);
+            let e = Diagnostic::new("E0304", name.span, message)
+                .with_note(Some(previous.span), "previously declared here");
+            errors.push(e);
+            continue;
+        }
+        functions.insert(name.name.clone(), FunctionInfo { parameters: function.params.len(), span: name.span });
+    }
+
+    match program.functions.iter().find(|f| f.name.name == "main") {
+    None => {
+        errors.push(Diagnostic::new("E0309", Span::default(), String::from("No 'main' function declared.")));
+    }
+    Some(main) => {
+        if !main.params.is_empty() {
+            errors.push(Diagnostic::new("E0310", main.params[0].span, String::from("'main' cannot take parameters.")));
+        }
+    }
+    }
+
+    for function in &program.functions {
+        let mut checker = Checker { functions: &functions, host, scopes: vec![HashMap::new()], loop_depth: 0, errors: vec![] };
+        for param in &function.params {
+            checker.declare(&param.name, VarType::Int);
+        }
+        checker.block(&function.body);
+        errors.append(&mut checker.errors);
+    }
+
+    if errors.is_empty() {
+        return Ok(());
+    }
+    // report in source order, errors without a location last.
+    errors.sort_by_key(|e| (e.span.line == 0, e.span.start));
+    return Err(errors);
+}
+
+impl Checker<'_> {
+    fn error(&mut self, code: &'static str, span: Span, message: String) {
+        self.errors.push(Diagnostic::new(code, span, message));
+    }
+
+    fn declare(&mut self, name: &Ident, ty: VarType) {
+        let scope = self.scopes.last_mut().unwrap();
+        if let Some((_, previous)) = scope.get(&name.name) {
+            let message = format!("variable '{}' is already declared.", name.name);
+            let e = Diagnostic::new("E0302", name.span, message)
+                .with_note(Some(*previous), "previously declared here");
+            self.errors.push(e);
+            return;
+        }
+        scope.insert(name.name.clone(), (ty, name.span));
+    }
+
+    fn lookup(&mut self, name: &str, span: Span) -> Option<(VarType, Span)> {
+        for scope in self.scopes.iter().rev() {
+            if let Some(found) = scope.get(name) {
+                return Some(*found);
+            }
+        }
+        self.error("E0301", span, format!("variable '{name}' has not been declared."));
+        None
+    }
+
+    // checks a use of 'name' where a plain integer is expected.
+    fn integer(&mut self, name: &str, span: Span) {
+        if let Some((VarType::Array(_), declared)) = self.lookup(name, span) {
+            let message = format!("'{name}' is an array, not an integer. use an index such as '{name}[0]'.");
+            let e = Diagnostic::new("E0306", span, message)
+                .with_note(Some(declared), "declared as an array here");
+            self.errors.push(e);
+        }
+    }
+
+    // checks a use of 'name[...]'.
+    fn array(&mut self, name: &Ident) {
+        if let Some((VarType::Int, declared)) = self.lookup(&name.name, name.span) {
+            let message = format!("'{}' is an integer, not an array.", name.name);
+            let e = Diagnostic::new("E0307", name.span, message)
+                .with_note(Some(declared), "declared as an integer here");
+            self.errors.push(e);
+        }
+    }
+
+    fn place(&mut self, place: &Place) {
+        match &place.index {
+        None => self.integer(&place.name.name, place.name.span),
+        Some(index) => {
+            self.array(&place.name);
+            self.expression(index);
+        }
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        self.scopes.push(HashMap::new());
+        for stmt in &block.stmts {
+            self.statement(stmt);
+        }
+        self.scopes.pop();
+    }
+
+    fn statement(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+
+        StmtKind::Declare { ty, name, init } => {
+            if let VarType::Array(len) = ty {
+                if *len <= 0 {
+                    self.error("E0311", name.span, format!("array '{}' must have a size greater than zero.", name.name));
+                }
+            }
+            if let Some(init) = init {
+                self.expression(init);
+            }
+            self.declare(name, *ty);
+        }
+
+        StmtKind::Assign { target, value } => {
+            self.place(target);
+            self.expression(value);
+        }
+
+        StmtKind::While { cond, body } => {
+            self.expression(cond);
+            self.loop_depth += 1;
+            self.block(body);
+            self.loop_depth -= 1;
+        }
+
+        StmtKind::If { cond, then_block, else_block } => {
+            self.expression(cond);
+            self.block(then_block);
+            if let Some(else_block) = else_block {
+                self.block(else_block);
+            }
+        }
+
+        StmtKind::Break => {
+            if self.loop_depth == 0 {
+                self.error("E0305", stmt.span, String::from("Used 'break' outside of a loop."));
+            }
+        }
+
+        StmtKind::Continue => {
+            if self.loop_depth == 0 {
+                self.error("E0305", stmt.span, String::from("Used 'continue' outside of a loop."));
+            }
+        }
+
+        StmtKind::Print(expr) | StmtKind::Return(expr) | StmtKind::Expr(expr) => {
+            self.expression(expr);
+        }
+
+        StmtKind::Read(place) => {
+            self.place(place);
+        }
+
+        }
+    }
+
+    fn expression(&mut self, expr: &Expr) {
+        match &expr.kind {
+        ExprKind::Num(_) => {}
+
+        ExprKind::Var(name) => self.integer(name, expr.span),
+
+        ExprKind::Index { array, index } => {
+            self.array(array);
+            self.expression(index);
+        }
+
+        ExprKind::Call { name, args } => {
+            for arg in args {
+                self.expression(arg);
+            }
+            let functions = self.functions;
+            match functions.get(&name.name) {
+            None => {
+                match self.host.find(&name.name) {
+                None => self.error("E0303", name.span, format!("function '{}' has not been declared.", name.name)),
+                Some(id) => {
+                    let arity = self.host.get(id).arity;
+                    if arity != args.len() {
+                        let message = format!("function '{}' expects {} argument(s) but {} were given.", name.name, arity, args.len());
+                        let e = Diagnostic::new("E0308", expr.span, message)
+                            .with_note(None, "it is a host function provided by the runtime");
+                        self.errors.push(e);
+                    }
+                }
+                }
+            }
+            Some(info) => {
+                if info.parameters != args.len() {
+                    let message = format!("function '{}' expects {} argument(s) but {} were given.", name.name, info.parameters, args.len());
+                    let e = Diagnostic::new("E0308", expr.span, message)
+                        .with_note(Some(info.span), "function declared here");
+                    self.errors.push(e);
+                }
+            }
+            }
+        }
+
+        ExprKind::Unary { expr, .. } => self.expression(expr),
+
+        ExprKind::Binary { lhs, rhs, .. } => {
+            self.expression(lhs);
+            self.expression(rhs);
+        }
+
+        }
+    }
+}
+
+#[cfg(test)]
+mod semantic_tests {
+    use crate::lexer::lex;
+    use crate::parser::parse_program;
+    use crate::semantic::*;
+
+    fn check(code: &str) -> Vec<Diagnostic> {
+        let program = parse_program(&lex(code).ok().unwrap()).ok().unwrap();
+        match check_program(&program, &HostRegistry::with_builtins()) {
+        Ok(()) => vec![],
+        Err(errors) => errors,
+        }
+    }
+
+    #[test]
+    fn semantic_valid() {
+        let errors = check("func f(int a) { return a; }\nfunc main() { int[3] a; int i = 0; while i < 3 { int i = 2; a[i] = f(i); if i == 2 { break; } } }");
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn semantic_errors() {
+        let errors = check("func main() {\n    int i;\n    break;\n    int i;\n    x = 1;\n}");
+        assert!(errors.len() == 3);
+        assert!(errors[0].code == "E0305" && errors[0].message.contains("'break' outside of a loop") && errors[0].span.line == 3);
+        assert!(errors[1].message.contains("already declared") && errors[1].span.line == 4 && errors[1].span.col == 9);
+        assert!(errors[1].code == "E0302" && errors[1].notes[0].span.unwrap().line == 2);
+        assert!(errors[2].message.contains("'x' has not been declared"));
+
+        let errors = check("func f(int a) { return a; }\nfunc f() { }\nfunc g() { int[2] a; int b; a = b[0]; f(a, 1); h(); }");
+        assert!(errors.len() == 7);
+        assert!(errors[0].message.contains("function 'f' is already declared"));
+        assert!(errors[1].message.contains("'a' is an array"));
+        assert!(errors[2].message.contains("'b' is an integer"));
+        assert!(errors[3].message.contains("expects 1 argument(s) but 2"));
+        assert!(errors[4].message.contains("'a' is an array"));
+        assert!(errors[5].message.contains("function 'h' has not been declared"));
+        assert!(errors[6].message.contains("No 'main'") && errors[6].span.line == 0);
+
+        // host functions can be called like functions of the program.
+        assert!(check("func main() { print(abs(-3) + time_ms()); }").is_empty());
+        let errors = check("func main() { print(abs(1, 2)); }");
+        assert!(errors.len() == 1 && errors[0].code == "E0308" && errors[0].notes[0].span.is_none());
+    }
+}
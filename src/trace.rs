@@ -0,0 +1,128 @@
+use std::io::{self, Write};
+
+use crate::interpreter::{HookAction, VmHook, VmState};
+
+// writes one line per executed instruction, indented by call depth:
+//
+//     fact[2] %lt _temp0, n=4, 2 -> 0
+//
+// the function, the instruction index, the instruction with the values it reads,
+// and the value it wrote or returned. two runs of the same program can be diffed.
+
+pub struct Tracer<'a> {
+    out: &'a mut dyn Write,
+
+    // only trace these functions, all of them when empty.
+    functions: Vec<String>,
+    limit: Option<usize>,
+    lines: usize,
+
+    // the instruction currently running, written once its result is known.
+    pending: Option<String>,
+    error: Option<io::Error>,
+}
+
+impl<'a> Tracer<'a> {
+    pub fn new(out: &'a mut dyn Write, functions: Vec<String>, limit: Option<usize>) -> Tracer<'a> {
+        Tracer { out, functions, limit, lines: 0, pending: None, error: None }
+    }
+
+    fn write(&mut self, line: &str) {
+        if self.error.is_none() {
+            if let Err(e) = writeln!(self.out, "{line}") {
+                self.error = Some(e);
+            }
+        }
+    }
+
+    // writes the instruction a runtime error stopped at, and flushes the trace.
+    pub fn finish(&mut self) -> io::Result<()> {
+        if let Some(line) = self.pending.take() {
+            self.write(&format!("{line} -> error"));
+        }
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.out.flush()
+    }
+}
+
+impl VmHook for Tracer<'_> {
+    fn before_instruction(&mut self, state: &VmState) -> HookAction {
+        if self.error.is_some() {
+            return HookAction::Stop;
+        }
+        if !self.functions.is_empty() && !self.functions.iter().any(|f| f == state.function().name()) {
+            return HookAction::Continue;
+        }
+
+        if let Some(limit) = self.limit {
+            if self.lines >= limit {
+                // say so once, then keep running without tracing.
+                if self.lines == limit {
+                    self.write(&format!("trace limit of {limit} lines reached."));
+                    self.lines += 1;
+                }
+                return HookAction::Continue;
+            }
+        }
+        self.lines += 1;
+
+        let indent = "  ".repeat(state.depth() - 1);
+        self.pending = Some(format!("{indent}{}[{}] {}", state.function().name(), state.ip(), state.disassemble()));
+        return HookAction::Continue;
+    }
+
+    fn after_instruction(&mut self, _state: &VmState, result: Option<i32>) {
+        if let Some(line) = self.pending.take() {
+            match result {
+            Some(value) => self.write(&format!("{line} -> {value}")),
+            None => self.write(&line),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod trace_tests {
+    use crate::host::HostRegistry;
+    use crate::interpreter::{parse_program, VmConfig};
+    use crate::trace::*;
+    use crate::vm_io::*;
+
+    fn trace(code: &str, functions: Vec<String>, limit: Option<usize>) -> String {
+        let mut host = HostRegistry::with_builtins();
+        let program = parse_program(code, &host).ok().unwrap();
+        let (mut input, mut output, mut reporter) = (MemoryInput::new("7"), MemoryOutput::default(), MemoryReporter::default());
+        let mut io = VmIo { input: &mut input, output: &mut output, reporter: &mut reporter };
+        let mut out: Vec<u8> = vec![];
+        let mut tracer = Tracer::new(&mut out, functions, limit);
+        let _ = program.run_with_hook(&VmConfig::default(), &mut io, &mut host, &mut tracer);
+        tracer.finish().ok().unwrap();
+        return String::from_utf8(out).unwrap();
+    }
+
+    #[test]
+    fn trace_lines() {
+        let code = "%func inc(%int x)\n    %add x, x, 1\n    %ret x\n%endfunc\n%func main()\n    %int a\n    %int[] b, 2\n    %input a\n    %call a, inc(a)\n    %mov [b + 1], a\n    %mov a, [b + 1]\n:end\n    %call a, abs(a)\n    %div a, a, 0\n%endfunc\n";
+        let lines = trace(code, vec![], None);
+        assert!(lines == "\
+main[0] %int a
+main[1] %int[] b, 2
+main[2] %input a -> 7
+main[3] %call a, inc(a=7)
+  inc[0] %add x, x=7, 1 -> 8
+  inc[1] %ret x=8 -> 8
+main[4] %mov [b + 1], a=8 -> 8
+main[5] %mov a, [b + 1]=8 -> 8
+main[6] :end
+main[7] %call a, abs(a=8) -> 8
+main[8] %div a, a=8, 0 -> error
+");
+
+        let lines = trace(code, vec![String::from("inc")], None);
+        assert!(lines == "  inc[0] %add x, x=7, 1 -> 8\n  inc[1] %ret x=8 -> 8\n");
+        let lines = trace(code, vec![], Some(2));
+        assert!(lines == "main[0] %int a\nmain[1] %int[] b, 2\ntrace limit of 2 lines reached.\n");
+    }
+}
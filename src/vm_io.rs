@@ -0,0 +1,170 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+
+// where the VM reads '%input' from, writes '%out' to, and reports its own status lines
+// ("Valid IR...", "Run successful...", runtime errors). keeping the status lines apart
+// means a run's output can be captured or compared without them.
+
+pub trait InputSource {
+    // same contract as BufRead::read_line, Ok(0) means the input has ended.
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize>;
+}
+
+pub trait OutputSink {
+    fn write_number(&mut self, value: i32) -> io::Result<()>;
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub trait Reporter {
+    fn report(&mut self, message: &str);
+}
+
+pub struct VmIo<'a> {
+    pub input: &'a mut dyn InputSource,
+    pub output: &'a mut dyn OutputSink,
+    pub reporter: &'a mut dyn Reporter,
+}
+
+// standard streams.
+
+pub struct StdinInput;
+
+impl InputSource for StdinInput {
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        io::stdin().read_line(buf)
+    }
+}
+
+pub struct StdoutOutput;
+
+impl OutputSink for StdoutOutput {
+    fn write_number(&mut self, value: i32) -> io::Result<()> {
+        writeln!(io::stdout().lock(), "{value}")
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+// status lines go to stderr, so stdout carries nothing but the program's '%out' numbers.
+pub struct StderrReporter;
+
+impl Reporter for StderrReporter {
+    fn report(&mut self, message: &str) {
+        eprintln!("{message}");
+    }
+}
+
+// in memory, for tests and for embedding the VM.
+
+pub struct MemoryInput {
+    text: String,
+    position: usize,
+}
+
+impl MemoryInput {
+    pub fn new(text: &str) -> MemoryInput {
+        MemoryInput { text: String::from(text), position: 0 }
+    }
+}
+
+impl InputSource for MemoryInput {
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        let rest = &self.text[self.position..];
+        let len = rest.find('\n').map_or(rest.len(), |i| i + 1);
+        buf.push_str(&rest[..len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryOutput {
+    pub values: Vec<i32>,
+}
+
+impl MemoryOutput {
+    // the output as the program would have printed it, one number per line.
+    pub fn text(&self) -> String {
+        self.values.iter().map(|value| format!("{value}\n")).collect()
+    }
+}
+
+impl OutputSink for MemoryOutput {
+    fn write_number(&mut self, value: i32) -> io::Result<()> {
+        self.values.push(value);
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryReporter {
+    pub lines: Vec<String>,
+}
+
+impl Reporter for MemoryReporter {
+    fn report(&mut self, message: &str) {
+        self.lines.extend(message.lines().map(String::from));
+    }
+}
+
+// files.
+
+pub struct FileInput {
+    reader: BufReader<File>,
+}
+
+impl FileInput {
+    pub fn open(path: &str) -> io::Result<FileInput> {
+        Ok(FileInput { reader: BufReader::new(File::open(path)?) })
+    }
+}
+
+impl InputSource for FileInput {
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        self.reader.read_line(buf)
+    }
+}
+
+pub struct FileOutput {
+    writer: BufWriter<File>,
+}
+
+impl FileOutput {
+    pub fn create(path: &str) -> io::Result<FileOutput> {
+        Ok(FileOutput { writer: BufWriter::new(File::create(path)?) })
+    }
+}
+
+impl OutputSink for FileOutput {
+    fn write_number(&mut self, value: i32) -> io::Result<()> {
+        writeln!(self.writer, "{value}")
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod vm_io_tests {
+    use crate::vm_io::*;
+
+    #[test]
+    fn memory_io() {
+        let mut input = MemoryInput::new("1 2\n3");
+        let mut buf = String::new();
+        assert!(input.read_line(&mut buf).ok() == Some(4) && buf == "1 2\n");
+        assert!(input.read_line(&mut buf).ok() == Some(1) && buf == "1 2\n3");
+        assert!(input.read_line(&mut buf).ok() == Some(0));
+
+        let mut output = MemoryOutput::default();
+        output.write_number(-4).ok().unwrap();
+        output.write_number(10).ok().unwrap();
+        assert!(output.text() == "-4\n10\n");
+    }
+}
@@ -0,0 +1,30 @@
+#![allow(clippy::needless_return)]
+
+use std::process::Command;
+
+// runs the rustcompiler binary on a .tt source written to a temporary file.
+fn run_cli(name: &str, source: &str, args: &[&str]) -> (i32, String, String) {
+    let path = std::env::temp_dir().join(format!("rustcompiler_cli_{}_{name}.tt", std::process::id()));
+    std::fs::write(&path, source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rustcompiler")).args(args).arg(&path).output().unwrap();
+    let _ = std::fs::remove_file(&path);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    return (output.status.code().unwrap(), stdout, stderr);
+}
+
+#[test]
+fn cli_streams() {
+    // only the program's output is on stdout.
+    let (status, stdout, stderr) = run_cli("ok", "func main() { print(3); return 1; }", &["run"]);
+    assert!(status == 1 && stdout == "3\n");
+    assert!(stderr.contains("Run successful. Exit code 1"));
+
+    // a compile error leaves stdout empty.
+    let (status, stdout, stderr) = run_cli("error", "func main() { print(x); }", &["run"]);
+    assert!(status == 65 && stdout.is_empty());
+    assert!(stderr.contains("error[E0301]"));
+
+    let (status, stdout, stderr) = run_cli("missing", "", &["run", "--no-such-option"]);
+    assert!(status == 64 && stdout.is_empty() && stderr.contains("Unknown option '--no-such-option'"));
+}